@@ -1,113 +1,1214 @@
+use crate::backend::{DataBackend, MemoryBackend};
 use crate::config::Config;
-use crate::requests::{api_delete, api_post, api_put};
+use crate::input::JsonInput;
+use crate::path::{JsonPath, Segment};
+use crate::requests::ApiError;
+use crate::worker::Worker;
+use color_eyre::eyre::{OptionExt, eyre};
 use serde_json::Value;
+use std::{error::Error, fmt, time::Instant};
 
 pub enum CurrentScreen {
     Main,
     Editing,
     Adding,
     Deleting,
+    Moving,
+    Reviewing,
 }
 
 pub struct CurrentlyEditing {
-    pub key: String,
-    pub value: String,
-    pub changed: bool,
+    pub key: Segment,
+    pub name: Option<String>,
+    pub focus: Field,
+    pub value: JsonInput,
+}
+
+impl CurrentlyEditing {
+    pub fn new(key: Segment, value: JsonInput) -> CurrentlyEditing {
+        let name = match &key {
+            Segment::Key(key) => Some(key.clone()),
+            Segment::Index(_) => None,
+        };
+        CurrentlyEditing {
+            key,
+            name,
+            focus: Field::Value,
+            value,
+        }
+    }
+
+    pub fn focus(&mut self, field: Field) {
+        self.value.set_focused(field == Field::Value);
+        self.focus = field;
+    }
+
+    pub fn renamed(&self) -> Option<&str> {
+        match (&self.key, &self.name) {
+            (Segment::Key(key), Some(name)) if key != name => Some(name),
+            _ => None,
+        }
+    }
+}
+
+#[derive(PartialEq)]
+pub enum Field {
+    Key,
+    Value,
 }
 
 pub struct CurrentlyAdding {
-    pub value: String,
+    pub key: Segment,
+    pub focus: Field,
+    pub value: JsonInput,
+}
+
+impl CurrentlyAdding {
+    pub fn new(key: Segment, value: JsonInput) -> CurrentlyAdding {
+        let mut adding = CurrentlyAdding {
+            focus: Field::Value,
+            key,
+            value,
+        };
+        if let Segment::Key(_) = adding.key {
+            adding.focus(Field::Key);
+        }
+        adding
+    }
+
+    pub fn focus(&mut self, field: Field) {
+        self.value.set_focused(field == Field::Value);
+        self.focus = field;
+    }
 }
 
 pub struct CurrentlyDeleting {
-    pub key: String,
+    pub key: Segment,
     pub are_you_sure: bool, //Pretty sure. Threw a trash bag into space at work.
 }
 
+pub struct CurrentlyMoving {
+    pub from: usize,
+    pub to: String,
+}
+
+pub struct ErrorPopup {
+    pub title: String,
+    pub message: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Change {
+    Value,
+    Rename { from: String, to: String },
+    Move { from: usize, to: usize },
+}
+
+#[derive(Clone, Debug)]
+pub struct Operation {
+    pub path: JsonPath,
+    pub previous: Option<Value>,
+    pub new: Option<Value>,
+    pub change: Change,
+}
+
+impl Operation {
+    pub fn inverse(&self) -> Operation {
+        Operation {
+            path: self.path.clone(),
+            previous: self.new.clone(),
+            new: self.previous.clone(),
+            change: match &self.change {
+                Change::Value => Change::Value,
+                Change::Rename { from, to } => Change::Rename {
+                    from: to.clone(),
+                    to: from.clone(),
+                },
+                Change::Move { from, to } => Change::Move {
+                    from: *to,
+                    to: *from,
+                },
+            },
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match &self.change {
+            Change::Rename { from, to } => format!(
+                "rename {} to {}",
+                self.path.join(Segment::Key(from.clone())),
+                self.path.join(Segment::Key(to.clone()))
+            ),
+            Change::Move { from, to } => format!(
+                "move {} to {}",
+                self.path.join(Segment::Index(*from)),
+                self.path.join(Segment::Index(*to))
+            ),
+            Change::Value => {
+                let action = match (&self.previous, &self.new) {
+                    (Some(_), Some(_)) => "edit",
+                    (None, Some(_)) => "add",
+                    (Some(_), None) => "delete",
+                    (None, None) => "noop",
+                };
+                format!("{} {}", action, self.path)
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Conflict {
+    pub operation: Operation,
+    pub remote: Option<Value>,
+    step: Step,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} was changed remotely", self.operation.path)
+    }
+}
+
+impl Error for Conflict {}
+
+pub struct Staging {
+    pub pending: Vec<Operation>,
+    pub working: MemoryBackend,
+}
+
+pub struct Request {
+    id: u64,
+    effect: Effect,
+    pub label: &'static str,
+    pub started: Instant,
+}
+
+impl Request {
+    pub fn is_cancellable(&self) -> bool {
+        !matches!(self.effect, Effect::Commit)
+    }
+}
+
+// A commit reports every write it made, so it has to run to completion.
+enum Effect {
+    Read,
+    Write,
+    Commit,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Step {
+    Execute,
+    Undo,
+    Redo,
+    Overwrite,
+}
+
+struct Written {
+    operation: Operation,
+    subtree: Option<(JsonPath, Value)>,
+}
+
+enum Outcome {
+    Refreshed(Value),
+    Applied {
+        step: Step,
+        written: Written,
+    },
+    Committed {
+        written: Vec<Written>,
+        error: Option<color_eyre::Report>,
+    },
+}
+
 pub struct App {
     pub json: Value,
     pub config: Config,
-    pub key_path: Vec<String>,
+    pub source: String,
+    worker: Worker<color_eyre::Result<Outcome>>,
+    pub request: Option<Request>,
+    cancelled: Vec<u64>,
+    pub key_path: JsonPath,
     pub locations: Vec<usize>,
     pub current_screen: CurrentScreen,
     pub currently_editing: Option<CurrentlyEditing>,
     pub currently_adding: Option<CurrentlyAdding>,
     pub currently_deleting: Option<CurrentlyDeleting>,
+    pub currently_moving: Option<CurrentlyMoving>,
+    pub clipboard: Option<(Segment, Value)>,
+    pub error: Option<ErrorPopup>,
+    pub conflict: Option<Conflict>,
+    pub history: Vec<Operation>,
+    pub undone: Vec<Operation>,
+    pub staging: Option<Staging>,
 }
 
 impl App {
-    pub fn new(json: Value, config: Config) -> App {
-        App {
+    pub fn new(config: Config, mut backend: Box<dyn DataBackend>) -> color_eyre::Result<App> {
+        let json = backend.get(&JsonPath::root())?;
+        let staging = config.staged.then(|| Staging {
+            pending: vec![],
+            working: MemoryBackend::new(json.clone()),
+        });
+        Ok(App {
             json,
             config,
-            key_path: vec![],
+            source: backend.describe(),
+            worker: Worker::spawn(backend),
+            request: None,
+            cancelled: vec![],
+            key_path: JsonPath::root(),
             locations: vec![],
             current_screen: CurrentScreen::Main,
             currently_editing: None,
             currently_adding: None,
             currently_deleting: None,
+            currently_moving: None,
+            clipboard: None,
+            error: None,
+            conflict: None,
+            history: vec![],
+            undone: vec![],
+            staging,
+        })
+    }
+
+    pub fn refresh(&mut self) {
+        self.submit("loading", Effect::Read, |backend| {
+            backend.get(&JsonPath::root()).map(Outcome::Refreshed)
+        });
+    }
+
+    fn refreshed(&mut self, json: Value) -> color_eyre::Result<()> {
+        self.json = json;
+        self.restage()
+    }
+
+    fn climb_to_container(&mut self) {
+        while !matches!(self.current(), Ok(Value::Object(_) | Value::Array(_)))
+            && self.key_path.pop().is_some()
+        {
+            self.locations.pop();
+            self.close_popups();
         }
     }
 
-    pub fn save_edited_value(&self) -> color_eyre::Result<()> {
-        if let Some(editing) = &self.currently_editing {
-            let mut key_path = self.key_path.clone();
-            key_path.push(editing.key.clone());
-            let path = key_path
-                .iter()
-                .map(|v| {
-                    format!(
-                        "/{}",
-                        match v.trim_start_matches('0') {
-                            "" => "0",
-                            s => s,
-                        }
-                    )
+    pub fn is_idle(&self) -> bool {
+        matches!(self.current_screen, CurrentScreen::Main)
+            && self.error.is_none()
+            && self.conflict.is_none()
+            && !self.is_busy()
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.worker.is_busy()
+    }
+
+    fn submit(
+        &mut self,
+        label: &'static str,
+        effect: Effect,
+        job: impl FnOnce(&mut dyn DataBackend) -> color_eyre::Result<Outcome> + Send + 'static,
+    ) {
+        self.request = Some(Request {
+            id: self.worker.send(Box::new(job)),
+            effect,
+            label,
+            started: Instant::now(),
+        });
+    }
+
+    fn submit_write(&mut self, step: Step, operation: Operation, checked: bool) {
+        let refetch = self.config.refetch_subtree;
+        self.submit("saving", Effect::Write, move |backend| {
+            write_synced(backend, operation, checked, refetch)
+                .map_err(|err| match err.downcast::<Conflict>() {
+                    Ok(conflict) => Conflict { step, ..conflict }.into(),
+                    Err(err) => err,
                 })
-                .collect::<String>();
-            api_put(&path, &editing.value, &self.config.api_key)?;
+                .map(|written| Outcome::Applied { step, written })
+        });
+    }
+
+    pub fn cancel(&mut self) {
+        if let Some(request) = self.request.take_if(|request| request.is_cancellable())
+            && matches!(request.effect, Effect::Write)
+        {
+            // The write may still land, so record it if it does and queue a reload behind it.
+            self.cancelled.push(request.id);
+            self.close_popups();
+            self.worker.send(Box::new(|backend| {
+                backend.get(&JsonPath::root()).map(Outcome::Refreshed)
+            }));
+        }
+    }
+
+    pub fn poll(&mut self) -> color_eyre::Result<()> {
+        while let Some((id, outcome)) = self.worker.try_recv() {
+            if self
+                .request
+                .as_ref()
+                .is_some_and(|request| request.id == id)
+            {
+                self.request = None;
+                self.finish(outcome)?;
+            } else if let Some(index) = self.cancelled.iter().position(|&other| other == id) {
+                self.cancelled.remove(index);
+                if let Ok(Outcome::Applied { step, written }) = outcome {
+                    self.record(step, written)?;
+                }
+            } else if let Ok(Outcome::Refreshed(json)) = outcome {
+                self.refreshed(json)?;
+            }
         }
         Ok(())
     }
 
-    pub fn push_object_to_array(&self) -> color_eyre::Result<()> {
-        if let Some(adding) = &self.currently_adding {
-            let key_path = self.key_path.clone();
-            let path = key_path
+    fn finish(&mut self, outcome: color_eyre::Result<Outcome>) -> color_eyre::Result<()> {
+        match outcome? {
+            Outcome::Refreshed(json) => self.refreshed(json)?,
+            Outcome::Applied { step, written } => {
+                self.record(step, written)?;
+                match step {
+                    Step::Execute => self.close_popups(),
+                    Step::Overwrite if self.staging.is_some() => self.commit(),
+                    _ => {}
+                }
+            }
+            Outcome::Committed { written, error } => {
+                for written in written {
+                    self.sync(&written)?;
+                    if let Some(staging) = &mut self.staging {
+                        staging.pending.remove(0);
+                    }
+                    self.history.push(written.operation);
+                }
+                self.undone.clear();
+                self.restage()?;
+                match error {
+                    Some(err) => return Err(err),
+                    None => self.current_screen = CurrentScreen::Main,
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn record(&mut self, step: Step, written: Written) -> color_eyre::Result<()> {
+        self.sync(&written)?;
+        match step {
+            Step::Execute => {
+                self.history.push(written.operation);
+                self.undone.clear();
+            }
+            Step::Undo => {
+                if let Some(operation) = self.history.pop() {
+                    self.undone.push(operation);
+                }
+            }
+            Step::Redo => {
+                self.undone.pop();
+                self.history.push(written.operation);
+            }
+            Step::Overwrite => {
+                self.history.push(written.operation);
+                self.undone.clear();
+                if let Some(staging) = &mut self.staging {
+                    staging.pending.remove(0);
+                    self.restage()?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn view(&self) -> &Value {
+        match &self.staging {
+            Some(staging) => staging.working.json(),
+            None => &self.json,
+        }
+    }
+
+    pub fn current(&self) -> color_eyre::Result<&Value> {
+        self.value_at(&self.key_path).ok_or_eyre("Pointer DNE")
+    }
+
+    pub fn selected(&self, index: Option<usize>) -> color_eyre::Result<(Segment, &Value)> {
+        entries(self.current()?)
+            .ok_or_eyre("Not an Array or Object")?
+            .into_iter()
+            .nth(index.ok_or_eyre("No Item Selected")?)
+            .ok_or_eyre("Out of Range")
+    }
+
+    pub fn is_pending(&self, path: &JsonPath) -> bool {
+        self.staging.as_ref().is_some_and(|staging| {
+            staging
+                .pending
                 .iter()
-                .map(|v| {
-                    format!(
-                        "/{}",
-                        match v.trim_start_matches('0') {
-                            "" => "0",
-                            s => s,
-                        }
-                    )
-                })
-                .collect::<String>();
-            api_post(&path, &adding.value, &self.config.api_key)?;
+                .any(|operation| operation.path.starts_with(path))
+        })
+    }
+
+    pub fn commit(&mut self) {
+        let pending = match &self.staging {
+            Some(staging) if !staging.pending.is_empty() => staging.pending.clone(),
+            _ => {
+                self.current_screen = CurrentScreen::Main;
+                return;
+            }
+        };
+        let refetch = self.config.refetch_subtree;
+        self.submit("committing", Effect::Commit, move |backend| {
+            let mut written = vec![];
+            for operation in pending {
+                match write_synced(backend, operation, true, refetch) {
+                    Ok(done) => written.push(done),
+                    Err(err) => {
+                        return Ok(Outcome::Committed {
+                            written,
+                            error: Some(err),
+                        });
+                    }
+                }
+            }
+            Ok(Outcome::Committed {
+                written,
+                error: None,
+            })
+        });
+    }
+
+    pub fn discard(&mut self) -> color_eyre::Result<()> {
+        if let Some(staging) = &mut self.staging {
+            staging.pending.clear();
+        }
+        self.restage()
+    }
+
+    fn restage(&mut self) -> color_eyre::Result<()> {
+        let mut result = Ok(());
+        if let Some(staging) = &mut self.staging {
+            staging.working = MemoryBackend::new(self.json.clone());
+            result = staging
+                .pending
+                .iter()
+                .try_for_each(|operation| write(&mut staging.working, operation));
+        }
+        self.climb_to_container();
+        result
+    }
+
+    pub fn close_popups(&mut self) {
+        self.currently_editing = None;
+        self.currently_adding = None;
+        self.currently_deleting = None;
+        self.currently_moving = None;
+        self.current_screen = CurrentScreen::Main;
+    }
+
+    pub fn paste(&mut self, text: &str) {
+        if let Some(editing) = &mut self.currently_editing {
+            match (&editing.focus, &mut editing.name) {
+                (Field::Key, Some(name)) => name.extend(text.chars().filter(|c| !c.is_control())),
+                _ => editing.value.paste(text),
+            }
+        }
+        if let Some(adding) = &mut self.currently_adding {
+            match (&adding.focus, &mut adding.key) {
+                (Field::Key, Segment::Key(key)) => {
+                    key.extend(text.chars().filter(|c| !c.is_control()))
+                }
+                _ => adding.value.paste(text),
+            }
+        }
+    }
+
+    pub fn show_error(&mut self, err: color_eyre::Report) {
+        let err = match err.downcast::<Conflict>() {
+            Ok(conflict) => {
+                self.conflict = Some(conflict);
+                return;
+            }
+            Err(err) => err,
+        };
+        self.error = Some(match err.downcast_ref::<ApiError>() {
+            Some(api_error) => ErrorPopup {
+                title: format!("HTTP {}", api_error.status),
+                message: api_error.body.clone(),
+            },
+            None => ErrorPopup {
+                title: String::from("Error"),
+                message: err
+                    .chain()
+                    .map(|cause| cause.to_string())
+                    .collect::<Vec<_>>()
+                    .join(": "),
+            },
+        });
+    }
+
+    pub fn save_edited_value(&mut self) -> color_eyre::Result<()> {
+        if let Some(editing) = &self.currently_editing {
+            let value = editing
+                .value
+                .parsed
+                .as_ref()
+                .map_err(|err| eyre!("{}", err))?;
+            let operation = match editing.renamed() {
+                Some(name) => {
+                    if let Some(err) = self.key_error(name) {
+                        return Err(eyre!(err));
+                    }
+                    let parent = self.current()?;
+                    let mut renamed = parent.as_object().ok_or_eyre("Not an Object")?.clone();
+                    renamed.remove(&editing.key.to_string());
+                    renamed.insert(name.to_string(), value.clone());
+                    Operation {
+                        path: self.key_path.clone(),
+                        previous: Some(parent.clone()),
+                        new: Some(Value::Object(renamed)),
+                        change: Change::Rename {
+                            from: editing.key.to_string(),
+                            to: name.to_string(),
+                        },
+                    }
+                }
+                None => {
+                    let path = self.key_path.join(editing.key.clone());
+                    Operation {
+                        previous: self.value_at(&path).cloned(),
+                        new: Some(value.clone()),
+                        path,
+                        change: Change::Value,
+                    }
+                }
+            };
+            self.execute(operation)?;
         }
         Ok(())
     }
 
-    pub fn delete_value(&self) -> color_eyre::Result<()> {
+    pub fn key_error(&self, key: &str) -> Option<&'static str> {
+        if key.is_empty() {
+            return Some("Key is empty");
+        }
+        match self.current() {
+            Ok(Value::Object(map)) if map.contains_key(key) => Some("Key already exists"),
+            _ => None,
+        }
+    }
+
+    pub fn add_value(&mut self) -> color_eyre::Result<()> {
+        if let Some(adding) = &self.currently_adding {
+            match &adding.key {
+                Segment::Key(key) => {
+                    if let Some(err) = self.key_error(key) {
+                        return Err(eyre!(err));
+                    }
+                }
+                Segment::Index(index) => {
+                    let len = self.current()?.as_array().ok_or_eyre("Not an Array")?.len();
+                    if *index > len {
+                        return Err(eyre!("Index out of range"));
+                    }
+                }
+            }
+            let value = adding
+                .value
+                .parsed
+                .as_ref()
+                .map_err(|err| eyre!("{}", err))?;
+            self.execute(Operation {
+                path: self.key_path.join(adding.key.clone()),
+                previous: None,
+                new: Some(value.clone()),
+                change: Change::Value,
+            })?;
+        }
+        Ok(())
+    }
+
+    pub fn delete_value(&mut self) -> color_eyre::Result<()> {
         if let Some(deleting) = &self.currently_deleting {
-            let mut key_path = self.key_path.clone();
-            key_path.push(deleting.key.clone());
-            let path = key_path
+            let path = self.key_path.join(deleting.key.clone());
+            self.execute(Operation {
+                previous: Some(
+                    self.value_at(&path)
+                        .ok_or_eyre("Couldn't Find Value")?
+                        .clone(),
+                ),
+                new: None,
+                path,
+                change: Change::Value,
+            })?;
+        }
+        Ok(())
+    }
+
+    pub fn move_target(&self) -> color_eyre::Result<usize> {
+        let moving = self
+            .currently_moving
+            .as_ref()
+            .ok_or_eyre("Nothing to Move")?;
+        let len = self.current()?.as_array().ok_or_eyre("Not an Array")?.len();
+        match moving.to.parse() {
+            Ok(to) if to < len => Ok(to),
+            _ => Err(eyre!(
+                "Index must be between 0 and {}",
+                len.saturating_sub(1)
+            )),
+        }
+    }
+
+    pub fn move_element(&mut self, from: usize, to: usize) -> color_eyre::Result<()> {
+        let array = self.current()?.as_array().ok_or_eyre("Not an Array")?;
+        if from >= array.len() || to >= array.len() {
+            return Err(eyre!("Index out of range"));
+        }
+        if from == to {
+            self.close_popups();
+            return Ok(());
+        }
+        let mut moved = array.clone();
+        let element = moved.remove(from);
+        moved.insert(to, element);
+        self.execute(Operation {
+            path: self.key_path.clone(),
+            previous: Some(Value::Array(array.clone())),
+            new: Some(Value::Array(moved)),
+            change: Change::Move { from, to },
+        })
+    }
+
+    pub fn yank(&mut self, index: Option<usize>) -> color_eyre::Result<()> {
+        let (key, value) = self.selected(index)?;
+        self.clipboard = Some((key, value.clone()));
+        Ok(())
+    }
+
+    pub fn cut(&mut self, index: Option<usize>) -> color_eyre::Result<()> {
+        self.yank(index)?;
+        if let Some((key, value)) = &self.clipboard {
+            self.execute(Operation {
+                path: self.key_path.join(key.clone()),
+                previous: Some(value.clone()),
+                new: None,
+                change: Change::Value,
+            })?;
+        }
+        Ok(())
+    }
+
+    pub fn paste_clipboard(
+        &mut self,
+        index: Option<usize>,
+        after: bool,
+    ) -> color_eyre::Result<Option<usize>> {
+        let (key, value) = self.clipboard.clone().ok_or_eyre("Clipboard is Empty")?;
+        match self.current()? {
+            Value::Array(array) => {
+                let index = match index {
+                    Some(index) => index + after as usize,
+                    None => 0,
+                }
+                .min(array.len());
+                self.execute(Operation {
+                    path: self.key_path.join(Segment::Index(index)),
+                    previous: None,
+                    new: Some(value),
+                    change: Change::Value,
+                })?;
+                Ok(Some(index))
+            }
+            Value::Object(_) => {
+                let key = match key {
+                    Segment::Key(key) => key,
+                    Segment::Index(_) => String::new(),
+                };
+                self.currently_adding = Some(CurrentlyAdding::new(
+                    Segment::Key(key),
+                    JsonInput::new(serde_json::to_string_pretty(&value)?),
+                ));
+                self.current_screen = CurrentScreen::Adding;
+                Ok(None)
+            }
+            _ => Err(eyre!("Not an Array or Object")),
+        }
+    }
+
+    pub fn undo(&mut self) -> color_eyre::Result<()> {
+        if let Some(staging) = &mut self.staging {
+            if let Some(operation) = staging.pending.pop() {
+                self.undone.push(operation);
+            }
+            return self.restage();
+        }
+        if let Some(operation) = self.history.last() {
+            self.submit_write(Step::Undo, operation.inverse(), true);
+        }
+        Ok(())
+    }
+
+    pub fn redo(&mut self) -> color_eyre::Result<()> {
+        if let Some(operation) = self.undone.last().cloned() {
+            self.apply(Step::Redo, operation)?;
+        }
+        Ok(())
+    }
+
+    fn execute(&mut self, operation: Operation) -> color_eyre::Result<()> {
+        self.apply(Step::Execute, operation)
+    }
+
+    fn apply(&mut self, step: Step, operation: Operation) -> color_eyre::Result<()> {
+        let Some(staging) = &mut self.staging else {
+            self.submit_write(step, operation, true);
+            return Ok(());
+        };
+        write(&mut staging.working, &operation)?;
+        staging.pending.push(operation);
+        self.climb_to_container();
+        match step {
+            Step::Redo => {
+                self.undone.pop();
+            }
+            _ => {
+                self.undone.clear();
+                self.close_popups();
+            }
+        }
+        Ok(())
+    }
+
+    fn sync(&mut self, written: &Written) -> color_eyre::Result<()> {
+        let mut local = MemoryBackend::new(std::mem::take(&mut self.json));
+        let result = match &written.subtree {
+            Some((path, json)) => local.put(path, json),
+            None => write(&mut local, &written.operation),
+        };
+        self.json = local.into_json();
+        self.climb_to_container();
+        result
+    }
+
+    pub fn overwrite_conflict(&mut self) {
+        if let Some(conflict) = self.conflict.take() {
+            let step = match conflict.step {
+                Step::Undo | Step::Redo => conflict.step,
+                _ => Step::Overwrite,
+            };
+            self.submit_write(step, conflict.operation, false);
+        }
+    }
+
+    pub fn reload_conflict(&mut self) {
+        if self.conflict.take().is_some()
+            && let Some(staging) = &mut self.staging
+        {
+            staging.pending.remove(0);
+        }
+        self.refresh();
+    }
+
+    pub fn merge_conflict(&mut self) -> color_eyre::Result<()> {
+        let Some(conflict) = &self.conflict else {
+            return Ok(());
+        };
+        let operation = &conflict.operation;
+        if operation.change != Change::Value {
+            return Err(eyre!(
+                "Renames and moves can't be merged, reload or overwrite instead"
+            ));
+        }
+        let (Some(base), Some(mine)) = (&operation.previous, &operation.new) else {
+            return Err(eyre!("Only edits can be merged"));
+        };
+        let merged = Operation {
+            new: Some(match &conflict.remote {
+                Some(theirs) => merge(base, mine, theirs),
+                None => mine.clone(),
+            }),
+            previous: conflict.remote.clone(),
+            path: operation.path.clone(),
+            change: Change::Value,
+        };
+        let step = conflict.step;
+        self.conflict = None;
+        match &mut self.staging {
+            Some(staging) => {
+                staging.pending[0] = merged;
+                self.current_screen = CurrentScreen::Reviewing;
+                self.refresh();
+            }
+            None => self.submit_write(step, merged, true),
+        }
+        Ok(())
+    }
+
+    fn value_at(&self, path: &JsonPath) -> Option<&Value> {
+        self.view().pointer(&path.to_pointer())
+    }
+}
+
+fn write_checked(backend: &mut dyn DataBackend, operation: &Operation) -> color_eyre::Result<()> {
+    // Array inserts shift their siblings, so only keys can be checked before adding.
+    let adds_key = matches!(operation.path.split_last(), Some((_, Segment::Key(_))));
+    if operation.previous.is_some() || adds_key {
+        let remote = backend.lookup(&operation.path)?;
+        if remote != operation.previous {
+            return Err(Conflict {
+                operation: operation.clone(),
+                remote,
+                step: Step::Execute,
+            }
+            .into());
+        }
+    }
+    write(backend, operation)
+}
+
+fn write_synced(
+    backend: &mut dyn DataBackend,
+    operation: Operation,
+    checked: bool,
+    refetch: bool,
+) -> color_eyre::Result<Written> {
+    match checked {
+        true => write_checked(backend, &operation)?,
+        false => write(backend, &operation)?,
+    }
+    let subtree = match refetch {
+        true => {
+            let path = match operation.path.split_last() {
+                Some((parent, _)) => parent,
+                None => JsonPath::root(),
+            };
+            let json = backend.get(&path)?;
+            Some((path, json))
+        }
+        false => None,
+    };
+    Ok(Written { operation, subtree })
+}
+
+fn merge(base: &Value, mine: &Value, theirs: &Value) -> Value {
+    if mine == base {
+        return theirs.clone();
+    }
+    let (Value::Object(base), Value::Object(mine), Value::Object(theirs)) = (base, mine, theirs)
+    else {
+        return mine.clone();
+    };
+    let mut merged = theirs.clone();
+    for key in base.keys().chain(mine.keys()) {
+        match (base.get(key), mine.get(key), theirs.get(key)) {
+            (base, mine, _) if base == mine => {}
+            (Some(base), Some(mine), Some(theirs)) => {
+                merged.insert(key.clone(), merge(base, mine, theirs));
+            }
+            (_, Some(mine), _) => {
+                merged.insert(key.clone(), mine.clone());
+            }
+            (_, None, _) => {
+                merged.remove(key);
+            }
+        }
+    }
+    Value::Object(merged)
+}
+
+fn write(backend: &mut dyn DataBackend, operation: &Operation) -> color_eyre::Result<()> {
+    let path = &operation.path;
+    match (&operation.previous, &operation.new) {
+        (Some(_), Some(new)) => backend.put(path, new),
+        (None, Some(new)) => backend.insert(path, new),
+        (Some(_), None) => backend.delete(path),
+        (None, None) => Ok(()),
+    }
+}
+
+pub fn entries(json: &Value) -> Option<Vec<(Segment, &Value)>> {
+    match json {
+        Value::Object(map) => Some(
+            map.iter()
+                .map(|(key, value)| (Segment::Key(key.clone()), value))
+                .collect(),
+        ),
+        Value::Array(array) => Some(
+            array
                 .iter()
-                .map(|v| {
-                    format!(
-                        "/{}",
-                        match v.trim_start_matches('0') {
-                            "" => "0",
-                            s => s,
-                        }
-                    )
-                })
-                .collect::<String>();
-            api_delete(&path, &self.config.api_key)?;
+                .enumerate()
+                .map(|(index, value)| (Segment::Index(index), value))
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn edit(pointer: &str, previous: Value, new: Value) -> Operation {
+        Operation {
+            path: JsonPath::parse(pointer).unwrap(),
+            previous: Some(previous),
+            new: Some(new),
+            change: Change::Value,
+        }
+    }
+
+    fn conflict(result: color_eyre::Result<()>) -> Conflict {
+        result.unwrap_err().downcast::<Conflict>().unwrap()
+    }
+
+    fn app(json: Value, staged: bool) -> App {
+        let config = Config {
+            staged,
+            ..Config::default()
+        };
+        App::new(config, Box::new(MemoryBackend::new(json))).unwrap()
+    }
+
+    fn settle(app: &mut App) -> color_eyre::Result<()> {
+        while app.is_busy() {
+            app.poll()?;
+            std::thread::yield_now();
         }
         Ok(())
     }
+
+    fn reloaded(app: &mut App) -> Value {
+        app.refresh();
+        settle(app).unwrap();
+        app.json.clone()
+    }
+
+    #[test]
+    fn undo_and_redo_move_operations_between_stacks() {
+        let mut app = app(json!({"a": 1}), false);
+        app.execute(edit("/a", json!(1), json!(2))).unwrap();
+        settle(&mut app).unwrap();
+        assert_eq!(app.history.len(), 1);
+
+        app.undo().unwrap();
+        settle(&mut app).unwrap();
+        assert_eq!((app.history.len(), app.undone.len()), (0, 1));
+        assert_eq!(reloaded(&mut app), json!({"a": 1}));
+
+        app.redo().unwrap();
+        settle(&mut app).unwrap();
+        assert_eq!((app.history.len(), app.undone.len()), (1, 0));
+        assert_eq!(reloaded(&mut app), json!({"a": 2}));
+    }
+
+    #[test]
+    fn new_operation_clears_redo_stack() {
+        let mut app = app(json!({"a": 1}), false);
+        app.execute(edit("/a", json!(1), json!(2))).unwrap();
+        settle(&mut app).unwrap();
+        app.undo().unwrap();
+        settle(&mut app).unwrap();
+        app.execute(add("/b", json!(3))).unwrap();
+        settle(&mut app).unwrap();
+        assert_eq!((app.history.len(), app.undone.len()), (1, 0));
+    }
+
+    #[test]
+    fn cancelled_write_is_still_recorded() {
+        let mut app = app(json!({"a": 1}), false);
+        app.current_screen = CurrentScreen::Editing;
+        app.execute(edit("/a", json!(1), json!(2))).unwrap();
+        app.cancel();
+        assert!(app.request.is_none());
+        assert!(matches!(app.current_screen, CurrentScreen::Main));
+        settle(&mut app).unwrap();
+        assert_eq!(app.history.len(), 1);
+        assert_eq!(app.json, json!({"a": 2}));
+    }
+
+    #[test]
+    fn undo_leaves_node_that_is_no_longer_a_container() {
+        let mut app = app(json!({"x": 5}), false);
+        app.execute(edit("/x", json!(5), json!({"k": 1}))).unwrap();
+        settle(&mut app).unwrap();
+        app.key_path.push(Segment::Key(String::from("x")));
+        app.locations.push(0);
+
+        app.undo().unwrap();
+        settle(&mut app).unwrap();
+        assert!(app.key_path.is_root());
+        assert!(app.locations.is_empty());
+    }
+
+    #[test]
+    fn refresh_leaves_node_that_was_deleted_remotely() {
+        let mut app = app(json!({"x": {"k": 1}}), false);
+        app.key_path.push(Segment::Key(String::from("x")));
+        app.locations.push(0);
+        app.refreshed(json!({"y": 1})).unwrap();
+        assert!(app.key_path.is_root());
+    }
+
+    #[test]
+    fn staged_undo_and_redo_edit_pending() {
+        let mut app = app(json!({"a": 1}), true);
+        app.execute(edit("/a", json!(1), json!(2))).unwrap();
+        app.execute(add("/b", json!(3))).unwrap();
+        assert_eq!(app.view(), &json!({"a": 2, "b": 3}));
+
+        app.undo().unwrap();
+        assert_eq!(app.staging.as_ref().unwrap().pending.len(), 1);
+        assert_eq!(app.view(), &json!({"a": 2}));
+
+        app.redo().unwrap();
+        assert_eq!(app.staging.as_ref().unwrap().pending.len(), 2);
+        assert_eq!(app.view(), &json!({"a": 2, "b": 3}));
+
+        app.discard().unwrap();
+        assert!(app.staging.as_ref().unwrap().pending.is_empty());
+        assert_eq!(app.view(), &json!({"a": 1}));
+        assert_eq!(app.json, json!({"a": 1}));
+    }
+
+    #[test]
+    fn restage_replays_pending_over_refreshed_json() {
+        let mut app = app(json!({"a": 1}), true);
+        app.execute(edit("/a", json!(1), json!(2))).unwrap();
+        app.refreshed(json!({"a": 1, "c": 4})).unwrap();
+        assert_eq!(app.view(), &json!({"a": 2, "c": 4}));
+        assert_eq!(app.staging.as_ref().unwrap().pending.len(), 1);
+    }
+
+    #[test]
+    fn commit_moves_pending_into_history() {
+        let mut app = app(json!({"a": 1}), true);
+        app.execute(edit("/a", json!(1), json!(2))).unwrap();
+        app.execute(add("/b", json!(3))).unwrap();
+        app.commit();
+        settle(&mut app).unwrap();
+        assert!(app.staging.as_ref().unwrap().pending.is_empty());
+        assert_eq!(app.history.len(), 2);
+        assert_eq!(reloaded(&mut app), json!({"a": 2, "b": 3}));
+    }
+
+    #[test]
+    fn commit_keeps_operations_from_first_conflict() {
+        let mut app = app(json!({"a": 1, "b": 1}), true);
+        // Pretend b changed since the backend was loaded, so writing it conflicts.
+        app.refreshed(json!({"a": 1, "b": 5})).unwrap();
+        app.execute(edit("/a", json!(1), json!(2))).unwrap();
+        app.execute(edit("/b", json!(5), json!(6))).unwrap();
+        app.commit();
+        let err = settle(&mut app).unwrap_err();
+        app.show_error(err);
+        assert!(app.conflict.is_some());
+        assert_eq!(app.staging.as_ref().unwrap().pending.len(), 1);
+        assert_eq!(app.history.len(), 1);
+
+        app.overwrite_conflict();
+        settle(&mut app).unwrap();
+        assert!(app.staging.as_ref().unwrap().pending.is_empty());
+        assert_eq!(app.history.len(), 2);
+        assert_eq!(reloaded(&mut app), json!({"a": 2, "b": 6}));
+    }
+
+    #[test]
+    fn writes_when_remote_is_unchanged() {
+        let mut remote = MemoryBackend::new(json!({"a": 1}));
+        write_checked(&mut remote, &edit("/a", json!(1), json!(2))).unwrap();
+        assert_eq!(remote.json(), &json!({"a": 2}));
+    }
+
+    #[test]
+    fn writes_past_concurrent_edit_of_another_key() {
+        let mut remote = MemoryBackend::new(json!({"a": 1, "b": 3}));
+        write_checked(&mut remote, &edit("/a", json!(1), json!(2))).unwrap();
+        assert_eq!(remote.json(), &json!({"a": 2, "b": 3}));
+    }
+
+    #[test]
+    fn refuses_concurrent_edit_of_same_key() {
+        let mut remote = MemoryBackend::new(json!({"a": 3}));
+        let conflict = conflict(write_checked(&mut remote, &edit("/a", json!(1), json!(2))));
+        assert_eq!(conflict.remote, Some(json!(3)));
+        assert_eq!(remote.json(), &json!({"a": 3}));
+    }
+
+    #[test]
+    fn reports_remote_delete_as_missing() {
+        let mut remote = MemoryBackend::new(json!({}));
+        let conflict = conflict(write_checked(&mut remote, &edit("/a", json!(1), json!(2))));
+        assert_eq!(conflict.remote, None);
+        assert_eq!(remote.json(), &json!({}));
+    }
+
+    fn add(pointer: &str, new: Value) -> Operation {
+        Operation {
+            path: JsonPath::parse(pointer).unwrap(),
+            previous: None,
+            new: Some(new),
+            change: Change::Value,
+        }
+    }
+
+    #[test]
+    fn adds_missing_key() {
+        let mut remote = MemoryBackend::new(json!({"a": 1}));
+        write_checked(&mut remote, &add("/b", json!(2))).unwrap();
+        assert_eq!(remote.json(), &json!({"a": 1, "b": 2}));
+    }
+
+    #[test]
+    fn refuses_add_of_key_created_remotely() {
+        let mut remote = MemoryBackend::new(json!({"a": 1}));
+        let conflict = conflict(write_checked(&mut remote, &add("/a", json!(2))));
+        assert_eq!(conflict.remote, Some(json!(1)));
+        assert_eq!(remote.json(), &json!({"a": 1}));
+    }
+
+    #[test]
+    fn inserts_array_elements_without_checking() {
+        let mut remote = MemoryBackend::new(json!({"a": [1, 3]}));
+        write_checked(&mut remote, &add("/a/1", json!(2))).unwrap();
+        assert_eq!(remote.json(), &json!({"a": [1, 2, 3]}));
+    }
+
+    #[test]
+    fn merges_edits_of_different_keys() {
+        let base = json!({"a": 1, "b": 1, "c": {"x": 1, "y": 1}});
+        let mine = json!({"a": 2, "b": 1, "c": {"x": 2, "y": 1}});
+        let theirs = json!({"a": 1, "b": 3, "c": {"x": 1, "y": 3}, "d": 4});
+        assert_eq!(
+            merge(&base, &mine, &theirs),
+            json!({"a": 2, "b": 3, "c": {"x": 2, "y": 3}, "d": 4})
+        );
+    }
+
+    #[test]
+    fn merge_prefers_mine_for_same_key() {
+        let base = json!({"a": 1, "b": 1});
+        let mine = json!({"a": 2, "b": 1});
+        let theirs = json!({"a": 3, "b": 1});
+        assert_eq!(merge(&base, &mine, &theirs), json!({"a": 2, "b": 1}));
+        assert_eq!(merge(&json!(1), &json!(2), &json!(3)), json!(2));
+    }
+
+    #[test]
+    fn merge_keeps_my_deletes_and_their_additions() {
+        let base = json!({"a": 1, "b": 1});
+        let mine = json!({"a": 1});
+        let theirs = json!({"a": 1, "b": 1, "c": 1});
+        assert_eq!(merge(&base, &mine, &theirs), json!({"a": 1, "c": 1}));
+    }
+
+    #[test]
+    fn merge_takes_theirs_when_mine_is_unchanged() {
+        let base = json!({"a": 1});
+        assert_eq!(merge(&base, &base, &json!([1, 2])), json!([1, 2]));
+        assert_eq!(merge(&json!(1), &json!(1), &json!(5)), json!(5));
+    }
 }
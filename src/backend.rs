@@ -0,0 +1,401 @@
+use crate::{
+    config::Config,
+    path::{JsonPath, Segment},
+    requests::{ApiClient, ApiError},
+};
+use color_eyre::eyre::{OptionExt, eyre};
+use reqwest::StatusCode;
+use serde_json::Value;
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+use tempfile::NamedTempFile;
+
+pub trait DataBackend: Send {
+    fn describe(&self) -> String;
+    fn get(&mut self, path: &JsonPath) -> color_eyre::Result<Value>;
+    fn lookup(&mut self, path: &JsonPath) -> color_eyre::Result<Option<Value>>;
+    fn put(&mut self, path: &JsonPath, value: &Value) -> color_eyre::Result<()>;
+    fn post(&mut self, path: &JsonPath, value: &Value) -> color_eyre::Result<()>;
+    fn delete(&mut self, path: &JsonPath) -> color_eyre::Result<()>;
+    #[allow(dead_code)]
+    fn patch(&mut self, path: &JsonPath, value: &Value) -> color_eyre::Result<()>;
+
+    fn insert(&mut self, path: &JsonPath, value: &Value) -> color_eyre::Result<()> {
+        let Some((parent, Segment::Index(index))) = path.split_last() else {
+            return self.put(path, value);
+        };
+        let mut json = self.get(&parent)?;
+        let array = json.as_array_mut().ok_or_eyre("Not an Array")?;
+        match index.cmp(&array.len()) {
+            std::cmp::Ordering::Less => {
+                array.insert(*index, value.clone());
+                self.put(&parent, &json)
+            }
+            std::cmp::Ordering::Equal => self.post(&parent, value),
+            std::cmp::Ordering::Greater => Err(eyre!("Index out of range")),
+        }
+    }
+}
+
+pub struct HttpBackend {
+    profile: String,
+    client: ApiClient,
+}
+
+impl HttpBackend {
+    pub fn new(config: &Config) -> color_eyre::Result<HttpBackend> {
+        Ok(HttpBackend {
+            profile: config.profile.clone(),
+            client: ApiClient::new(config)?,
+        })
+    }
+}
+
+impl DataBackend for HttpBackend {
+    fn describe(&self) -> String {
+        format!("profile: {}", self.profile)
+    }
+
+    fn get(&mut self, path: &JsonPath) -> color_eyre::Result<Value> {
+        self.client.get(path)
+    }
+
+    fn lookup(&mut self, path: &JsonPath) -> color_eyre::Result<Option<Value>> {
+        match self.client.get(path) {
+            Ok(json) => Ok(Some(json)),
+            Err(err)
+                if err
+                    .downcast_ref::<ApiError>()
+                    .is_some_and(|err| err.status == StatusCode::NOT_FOUND) =>
+            {
+                Ok(None)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    fn put(&mut self, path: &JsonPath, value: &Value) -> color_eyre::Result<()> {
+        self.client.put(path, value)?;
+        Ok(())
+    }
+
+    fn post(&mut self, path: &JsonPath, value: &Value) -> color_eyre::Result<()> {
+        self.client.post(path, value)?;
+        Ok(())
+    }
+
+    fn delete(&mut self, path: &JsonPath) -> color_eyre::Result<()> {
+        self.client.delete(path)?;
+        Ok(())
+    }
+
+    fn patch(&mut self, path: &JsonPath, value: &Value) -> color_eyre::Result<()> {
+        self.client.patch(path, value)?;
+        Ok(())
+    }
+}
+
+pub struct MemoryBackend {
+    json: Value,
+}
+
+impl MemoryBackend {
+    pub fn new(json: Value) -> MemoryBackend {
+        MemoryBackend { json }
+    }
+
+    pub fn json(&self) -> &Value {
+        &self.json
+    }
+
+    pub fn into_json(self) -> Value {
+        self.json
+    }
+
+    fn parent_mut(&mut self, path: &JsonPath) -> color_eyre::Result<(&mut Value, Segment)> {
+        let (parent, key) = path.split_last().ok_or_eyre("Path has no parent")?;
+        let parent = self
+            .json
+            .pointer_mut(&parent.to_pointer())
+            .ok_or_eyre("Pointer DNE")?;
+        Ok((parent, key.clone()))
+    }
+}
+
+impl DataBackend for MemoryBackend {
+    fn describe(&self) -> String {
+        String::from("in memory")
+    }
+
+    fn get(&mut self, path: &JsonPath) -> color_eyre::Result<Value> {
+        self.json
+            .pointer(&path.to_pointer())
+            .cloned()
+            .ok_or_eyre("Pointer DNE")
+    }
+
+    fn lookup(&mut self, path: &JsonPath) -> color_eyre::Result<Option<Value>> {
+        Ok(self.json.pointer(&path.to_pointer()).cloned())
+    }
+
+    fn put(&mut self, path: &JsonPath, value: &Value) -> color_eyre::Result<()> {
+        if path.is_root() {
+            self.json = value.clone();
+            return Ok(());
+        }
+        match self.parent_mut(path)? {
+            (Value::Object(map), key) => {
+                map.insert(key.to_string(), value.clone());
+            }
+            (Value::Array(array), Segment::Index(index)) => match index.cmp(&array.len()) {
+                std::cmp::Ordering::Less => array[index] = value.clone(),
+                std::cmp::Ordering::Equal => array.push(value.clone()),
+                std::cmp::Ordering::Greater => return Err(eyre!("Index out of range")),
+            },
+            (Value::Array(_), Segment::Key(key)) => {
+                return Err(eyre!("\"{}\" is not an array index", key));
+            }
+            _ => return Err(eyre!("Not an Array or Object")),
+        }
+        Ok(())
+    }
+
+    fn post(&mut self, path: &JsonPath, value: &Value) -> color_eyre::Result<()> {
+        self.json
+            .pointer_mut(&path.to_pointer())
+            .ok_or_eyre("Pointer DNE")?
+            .as_array_mut()
+            .ok_or_eyre("Not an Array")?
+            .push(value.clone());
+        Ok(())
+    }
+
+    fn delete(&mut self, path: &JsonPath) -> color_eyre::Result<()> {
+        match self.parent_mut(path)? {
+            (Value::Object(map), key) => {
+                map.remove(&key.to_string())
+                    .ok_or_eyre("Couldn't Find Value")?;
+            }
+            (Value::Array(array), Segment::Index(index)) => {
+                if index >= array.len() {
+                    return Err(eyre!("Index out of range"));
+                }
+                array.remove(index);
+            }
+            (Value::Array(_), Segment::Key(key)) => {
+                return Err(eyre!("\"{}\" is not an array index", key));
+            }
+            _ => return Err(eyre!("Not an Array or Object")),
+        }
+        Ok(())
+    }
+
+    fn patch(&mut self, path: &JsonPath, value: &Value) -> color_eyre::Result<()> {
+        merge_patch(
+            self.json
+                .pointer_mut(&path.to_pointer())
+                .ok_or_eyre("Pointer DNE")?,
+            value,
+        );
+        Ok(())
+    }
+}
+
+fn merge_patch(target: &mut Value, patch: &Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(Default::default());
+    }
+    if let Value::Object(map) = target {
+        for (key, value) in patch {
+            if value.is_null() {
+                map.remove(key);
+            } else {
+                merge_patch(map.entry(key).or_insert(Value::Null), value);
+            }
+        }
+    }
+}
+
+pub struct FileBackend {
+    path: PathBuf,
+    memory: MemoryBackend,
+}
+
+impl FileBackend {
+    pub fn open(path: PathBuf) -> color_eyre::Result<FileBackend> {
+        let content = fs::read_to_string(&path)?;
+        Ok(FileBackend {
+            path,
+            memory: MemoryBackend::new(serde_json::from_str(&content)?),
+        })
+    }
+
+    fn write(&self) -> color_eyre::Result<()> {
+        let dir = match self.path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let mut file = NamedTempFile::new_in(dir)?;
+        serde_json::to_writer_pretty(&mut file, &self.memory.json)?;
+        writeln!(file)?;
+        file.as_file().sync_all()?;
+        if let Ok(metadata) = fs::metadata(&self.path) {
+            fs::set_permissions(file.path(), metadata.permissions())?;
+        }
+        file.persist(&self.path)?;
+        Ok(())
+    }
+}
+
+impl DataBackend for FileBackend {
+    fn describe(&self) -> String {
+        format!("file: {}", self.path.display())
+    }
+
+    fn get(&mut self, path: &JsonPath) -> color_eyre::Result<Value> {
+        self.memory.get(path)
+    }
+
+    fn lookup(&mut self, path: &JsonPath) -> color_eyre::Result<Option<Value>> {
+        self.memory.lookup(path)
+    }
+
+    fn put(&mut self, path: &JsonPath, value: &Value) -> color_eyre::Result<()> {
+        self.memory.put(path, value)?;
+        self.write()
+    }
+
+    fn post(&mut self, path: &JsonPath, value: &Value) -> color_eyre::Result<()> {
+        self.memory.post(path, value)?;
+        self.write()
+    }
+
+    fn delete(&mut self, path: &JsonPath) -> color_eyre::Result<()> {
+        self.memory.delete(path)?;
+        self.write()
+    }
+
+    fn patch(&mut self, path: &JsonPath, value: &Value) -> color_eyre::Result<()> {
+        self.memory.patch(path, value)?;
+        self.write()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn path(pointer: &str) -> JsonPath {
+        JsonPath::parse(pointer).unwrap()
+    }
+
+    #[test]
+    fn memory_put_replaces_and_appends() {
+        let mut memory = MemoryBackend::new(json!({"a": 1, "arr": [1, 2]}));
+        memory.put(&path("/a"), &json!(2)).unwrap();
+        memory.put(&path("/b"), &json!(3)).unwrap();
+        memory.put(&path("/arr/0"), &json!(0)).unwrap();
+        memory.put(&path("/arr/2"), &json!(3)).unwrap();
+        assert_eq!(memory.json(), &json!({"a": 2, "b": 3, "arr": [0, 2, 3]}));
+        assert!(memory.put(&path("/arr/5"), &json!(5)).is_err());
+        assert!(memory.put(&path("/arr/x"), &json!(5)).is_err());
+        assert!(memory.put(&path("/a/b"), &json!(5)).is_err());
+        memory.put(&JsonPath::root(), &json!([])).unwrap();
+        assert_eq!(memory.json(), &json!([]));
+    }
+
+    #[test]
+    fn memory_delete_removes_keys_and_shifts_elements() {
+        let mut memory = MemoryBackend::new(json!({"a": 1, "arr": [1, 2, 3]}));
+        memory.delete(&path("/a")).unwrap();
+        memory.delete(&path("/arr/0")).unwrap();
+        assert_eq!(memory.json(), &json!({"arr": [2, 3]}));
+        assert!(memory.delete(&path("/a")).is_err());
+        assert!(memory.delete(&path("/arr/2")).is_err());
+        assert!(memory.delete(&JsonPath::root()).is_err());
+    }
+
+    #[test]
+    fn memory_lookup_reports_missing_paths() {
+        let mut memory = MemoryBackend::new(json!({"a": [1]}));
+        assert_eq!(memory.lookup(&path("/a/0")).unwrap(), Some(json!(1)));
+        assert_eq!(memory.lookup(&path("/b")).unwrap(), None);
+        assert!(memory.get(&path("/b")).is_err());
+    }
+
+    #[test]
+    fn insert_shifts_appends_and_puts_keys() {
+        let mut memory = MemoryBackend::new(json!({"arr": [1, 3]}));
+        memory.insert(&path("/arr/1"), &json!(2)).unwrap();
+        memory.insert(&path("/arr/3"), &json!(4)).unwrap();
+        memory.insert(&path("/k"), &json!(5)).unwrap();
+        assert_eq!(memory.json(), &json!({"arr": [1, 2, 3, 4], "k": 5}));
+        assert!(memory.insert(&path("/arr/9"), &json!(9)).is_err());
+        assert!(memory.insert(&path("/k/0"), &json!(9)).is_err());
+    }
+
+    #[test]
+    fn merge_patch_follows_rfc_7396() {
+        let mut target = json!({"a": 1, "b": {"c": 1, "d": 1}, "e": [1]});
+        merge_patch(
+            &mut target,
+            &json!({"a": null, "b": {"c": 2, "x": {"y": 1}}, "e": {"f": 1}}),
+        );
+        assert_eq!(
+            target,
+            json!({"b": {"c": 2, "d": 1, "x": {"y": 1}}, "e": {"f": 1}})
+        );
+        merge_patch(&mut target, &json!([1]));
+        assert_eq!(target, json!([1]));
+    }
+
+    #[test]
+    fn file_writes_are_persisted() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("data.json");
+        fs::write(&file, "{\"a\": 1}").unwrap();
+        let mut backend = FileBackend::open(file.clone()).unwrap();
+        backend.put(&path("/b"), &json!([1])).unwrap();
+        backend.post(&path("/b"), &json!(2)).unwrap();
+        backend.delete(&path("/a")).unwrap();
+        let content = fs::read_to_string(&file).unwrap();
+        assert!(content.ends_with("}\n"));
+        assert_eq!(
+            serde_json::from_str::<Value>(&content).unwrap(),
+            json!({"b": [1, 2]})
+        );
+        assert_eq!(
+            FileBackend::open(file)
+                .unwrap()
+                .get(&JsonPath::root())
+                .unwrap(),
+            json!({"b": [1, 2]})
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn file_writes_keep_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("data.json");
+        fs::write(&file, "{}").unwrap();
+        fs::set_permissions(&file, fs::Permissions::from_mode(0o640)).unwrap();
+        FileBackend::open(file.clone())
+            .unwrap()
+            .put(&path("/a"), &json!(1))
+            .unwrap();
+        assert_eq!(
+            fs::metadata(&file).unwrap().permissions().mode() & 0o777,
+            0o640
+        );
+    }
+}
@@ -0,0 +1,75 @@
+use clap::{Args, Parser, Subcommand};
+use std::path::PathBuf;
+
+#[derive(Parser)]
+#[command(
+    version,
+    about = "Keyboard-driven editor for the NN data API",
+    after_help = "The API key is taken from the first of these that is set:
+  1. NAMEFUL_API_KEY for the default_profile from config.toml (or \"default\"),
+     or NAMEFUL_API_KEY_<PROFILE> for any other
+     (e.g. NAMEFUL_API_KEY_LOCAL_DEV for \"local-dev\")
+  2. the stdout of the profile's api_key_command, e.g. \"pass show nn/key\"
+  3. the contents of the profile's api_key_file, which must not be world-readable
+  4. the profile's api_key in config.toml"
+)]
+pub struct Cli {
+    #[arg(
+        long,
+        short,
+        env = "NAMEFUL_PROFILE",
+        help = "Connection profile from config.toml to use"
+    )]
+    pub profile: Option<String>,
+    #[arg(long, env = "NAMEFUL_BASE_URL", help = "Base URL of the data API")]
+    pub base_url: Option<String>,
+    #[arg(long, help = "Edit a local JSON file instead of the API")]
+    pub file: Option<PathBuf>,
+    #[arg(long, help = "Stage changes locally until they are committed")]
+    pub staged: bool,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    #[command(about = "Print the value at a JSON pointer path")]
+    Get {
+        #[arg(default_value = "", help = "JSON pointer path, e.g. /some/path")]
+        path: String,
+        #[arg(long, help = "Print compact JSON instead of pretty-printing it")]
+        compact: bool,
+    },
+    #[command(about = "Replace the value at a JSON pointer path")]
+    Set {
+        #[arg(help = "JSON pointer path, e.g. /some/path")]
+        path: String,
+        #[command(flatten)]
+        value: ValueSource,
+    },
+    #[command(about = "Append a value to the array at a JSON pointer path")]
+    Push {
+        #[arg(help = "JSON pointer path of the array")]
+        path: String,
+        #[command(flatten)]
+        value: ValueSource,
+    },
+    #[command(about = "Delete the value at a JSON pointer path")]
+    Delete {
+        #[arg(help = "JSON pointer path, e.g. /some/path")]
+        path: String,
+    },
+}
+
+#[derive(Args)]
+pub struct ValueSource {
+    #[arg(help = "JSON value, read from stdin if omitted or \"-\"")]
+    pub json: Option<String>,
+    #[arg(
+        long,
+        short,
+        conflicts_with = "json",
+        help = "Read the JSON value from a file"
+    )]
+    pub input: Option<PathBuf>,
+}
@@ -0,0 +1,54 @@
+use crate::{
+    backend::DataBackend,
+    cli::{Command, ValueSource},
+    config::Config,
+    path::JsonPath,
+};
+use color_eyre::eyre::eyre;
+use serde_json::Value;
+use std::{
+    fs,
+    io::{self, Read},
+};
+
+pub fn run(
+    command: Command,
+    config: &Config,
+    mut backend: Box<dyn DataBackend>,
+) -> color_eyre::Result<()> {
+    if config.read_only && !matches!(command, Command::Get { .. }) {
+        return Err(eyre!("profile \"{}\" is read-only", config.profile));
+    }
+    match command {
+        Command::Get { path, compact } => {
+            let json = backend.get(&JsonPath::parse(&path)?)?;
+            print_json(&json, compact)
+        }
+        Command::Set { path, value } => backend.put(&JsonPath::parse(&path)?, &read_value(value)?),
+        Command::Push { path, value } => {
+            backend.post(&JsonPath::parse(&path)?, &read_value(value)?)
+        }
+        Command::Delete { path } => backend.delete(&JsonPath::parse(&path)?),
+    }
+}
+
+fn read_value(source: ValueSource) -> color_eyre::Result<Value> {
+    let content = match (source.json, source.input) {
+        (Some(json), _) if json != "-" => json,
+        (_, Some(input)) => fs::read_to_string(input)?,
+        _ => {
+            let mut content = String::new();
+            io::stdin().read_to_string(&mut content)?;
+            content
+        }
+    };
+    Ok(serde_json::from_str(&content)?)
+}
+
+fn print_json(json: &Value, compact: bool) -> color_eyre::Result<()> {
+    match compact {
+        true => println!("{}", serde_json::to_string(json)?),
+        false => println!("{}", serde_json::to_string_pretty(json)?),
+    }
+    Ok(())
+}
@@ -1,29 +1,202 @@
-use color_eyre::eyre::OptionExt;
+use crate::cli::Cli;
+use color_eyre::eyre::{OptionExt, eyre};
 use serde::Deserialize;
-use std::{fs, io::Write};
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    io::Write,
+    path::{Path, PathBuf},
+    process,
+    time::Duration,
+};
 use xdg::BaseDirectories;
 
+const DEFAULT_PROFILE: &str = "default";
+const API_KEY_VAR: &str = "NAMEFUL_API_KEY";
+
 #[derive(Deserialize)]
+struct ConfigFile {
+    default_profile: Option<String>,
+    #[serde(default)]
+    profiles: BTreeMap<String, Profile>,
+    #[serde(flatten)]
+    top_level: Profile,
+}
+
+#[derive(Deserialize, Clone)]
+struct Profile {
+    #[serde(default)]
+    api_key: String,
+    api_key_command: Option<String>,
+    api_key_file: Option<PathBuf>,
+    #[serde(default = "default_base_url")]
+    base_url: String,
+    timeout: Option<u64>,
+    connect_timeout: Option<u64>,
+    #[serde(default = "default_retries")]
+    retries: u32,
+    #[serde(default)]
+    read_only: bool,
+    #[serde(default)]
+    staged: bool,
+    #[serde(default)]
+    refetch_subtree: bool,
+    refresh_interval: Option<u64>,
+}
+
+#[derive(Clone, Default)]
 pub struct Config {
-    pub api_key: String,
+    pub profile: String,
+    pub api_key: ApiKey,
+    pub base_url: String,
+    pub timeout: Option<Duration>,
+    pub connect_timeout: Option<Duration>,
+    pub retries: u32,
+    pub read_only: bool,
+    pub staged: bool,
+    pub refetch_subtree: bool,
+    pub refresh_interval: Option<Duration>,
+}
+
+#[derive(Clone, Default)]
+pub struct ApiKey {
+    var: String,
+    command: Option<String>,
+    file: Option<PathBuf>,
+    value: String,
+}
+
+fn default_base_url() -> String {
+    String::from("https://newnameful.com/api/data")
+}
+
+fn default_retries() -> u32 {
+    3
 }
 
 impl Config {
-    pub fn init() -> color_eyre::Result<Config> {
+    pub fn init(cli: &Cli) -> color_eyre::Result<Config> {
         let xdg_dirs = BaseDirectories::with_prefix("nameful-cli");
         let config_path = xdg_dirs.place_config_file("config.toml")?;
-        if xdg_dirs.find_config_file(&config_path) == None {
+        if xdg_dirs.find_config_file(&config_path).is_none() {
             let mut config_file = fs::File::create(&config_path)?;
-            write!(&mut config_file, "api_key = \"\"")?;
+            writeln!(&mut config_file, "# api_key_command = \"pass show nn/key\"")?;
+            writeln!(&mut config_file, "# api_key_file = \"/path/to/key\"")?;
+        }
+        let mut config = Config::new(cli.profile.as_deref())?;
+        if let Some(base_url) = &cli.base_url {
+            config.base_url = base_url.clone();
         }
-        Config::new()
+        config.base_url = config.base_url.trim_end_matches('/').to_string();
+        config.staged |= cli.staged;
+        Ok(config)
     }
-    pub fn new() -> color_eyre::Result<Config> {
+    pub fn new(profile: Option<&str>) -> color_eyre::Result<Config> {
         let xdg_dirs = BaseDirectories::with_prefix("nameful-cli");
         let config_path = xdg_dirs
             .find_config_file("config.toml")
             .ok_or_eyre("could not find config toml")?;
         let content = fs::read_to_string(&config_path)?;
-        Ok(toml::from_str(&content)?)
+        let file: ConfigFile = toml::from_str(&content)?;
+        let default_profile = file
+            .default_profile
+            .unwrap_or(String::from(DEFAULT_PROFILE));
+        let name = profile.map(String::from).unwrap_or(default_profile.clone());
+        let settings = match file.profiles.get(&name) {
+            Some(settings) => settings.clone(),
+            None if name == DEFAULT_PROFILE => file.top_level,
+            None => return Err(eyre!("no profile named \"{}\" in config toml", name)),
+        };
+        let api_key = ApiKey {
+            var: api_key_var(&name, name == default_profile),
+            command: settings.api_key_command,
+            file: settings.api_key_file,
+            value: settings.api_key,
+        };
+        Ok(Config {
+            profile: name,
+            api_key,
+            base_url: settings.base_url,
+            timeout: settings.timeout.map(Duration::from_secs),
+            connect_timeout: settings.connect_timeout.map(Duration::from_secs),
+            retries: settings.retries,
+            read_only: settings.read_only,
+            staged: settings.staged,
+            refetch_subtree: settings.refetch_subtree,
+            refresh_interval: settings.refresh_interval.map(Duration::from_secs),
+        })
+    }
+}
+
+// Each profile has its own variable, so a key exported for one is not sent when another is selected.
+fn api_key_var(profile: &str, is_default: bool) -> String {
+    if is_default {
+        return String::from(API_KEY_VAR);
+    }
+    let suffix: String = profile
+        .chars()
+        .map(|c| match c.is_ascii_alphanumeric() {
+            true => c.to_ascii_uppercase(),
+            false => '_',
+        })
+        .collect();
+    format!("{}_{}", API_KEY_VAR, suffix)
+}
+
+impl ApiKey {
+    pub fn resolve(&self) -> color_eyre::Result<String> {
+        if let Ok(api_key) = env::var(&self.var)
+            && !api_key.is_empty()
+        {
+            return Ok(api_key);
+        }
+        if let Some(command) = &self.command {
+            let output = process::Command::new("sh")
+                .arg("-c")
+                .arg(command)
+                .stderr(process::Stdio::inherit())
+                .output()?;
+            if !output.status.success() {
+                return Err(eyre!("api_key_command failed with {}", output.status));
+            }
+            return Ok(String::from_utf8(output.stdout)?.trim().to_string());
+        }
+        if let Some(path) = &self.file {
+            check_permissions(path)?;
+            return Ok(fs::read_to_string(path)?.trim().to_string());
+        }
+        Ok(self.value.clone())
+    }
+}
+
+#[cfg(unix)]
+fn check_permissions(path: &Path) -> color_eyre::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    let mode = fs::metadata(path)?.permissions().mode();
+    if mode & 0o004 != 0 {
+        return Err(eyre!(
+            "refusing to read world-readable api_key_file {} (mode {:o}), run chmod o-r on it",
+            path.display(),
+            mode & 0o777
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn check_permissions(_path: &Path) -> color_eyre::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scopes_api_key_var_to_profile() {
+        assert_eq!(api_key_var(DEFAULT_PROFILE, true), "NAMEFUL_API_KEY");
+        assert_eq!(api_key_var("prod", true), "NAMEFUL_API_KEY");
+        assert_eq!(api_key_var("prod", false), "NAMEFUL_API_KEY_PROD");
+        assert_eq!(api_key_var("local-dev", false), "NAMEFUL_API_KEY_LOCAL_DEV");
     }
 }
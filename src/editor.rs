@@ -0,0 +1,25 @@
+use color_eyre::eyre::eyre;
+use serde_json::Value;
+use std::{env, fs, io::Write, process::Command};
+
+pub fn edit_in_editor(value: &Value) -> color_eyre::Result<String> {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or(String::from("vi"));
+    let mut file = tempfile::Builder::new()
+        .prefix("nameful-")
+        .suffix(".json")
+        .tempfile()?;
+    writeln!(file, "{}", serde_json::to_string_pretty(value)?)?;
+    file.flush()?;
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$1\"", editor))
+        .arg("sh")
+        .arg(file.path())
+        .status()?;
+    if !status.success() {
+        return Err(eyre!("{} exited with {}", editor, status));
+    }
+    Ok(fs::read_to_string(file.path())?)
+}
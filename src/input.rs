@@ -0,0 +1,61 @@
+use ratatui::{
+    crossterm::event::KeyEvent,
+    style::{Modifier, Style},
+};
+use serde_json::Value;
+use tui_textarea::TextArea;
+
+pub struct JsonInput {
+    pub textarea: TextArea<'static>,
+    pub parsed: serde_json::Result<Value>,
+}
+
+impl JsonInput {
+    pub fn new(text: String) -> JsonInput {
+        let mut textarea = TextArea::from(text.lines());
+        textarea.set_cursor_line_style(Style::default());
+        textarea.move_cursor(tui_textarea::CursorMove::Bottom);
+        textarea.move_cursor(tui_textarea::CursorMove::End);
+        let parsed = serde_json::from_str(&text);
+        JsonInput { textarea, parsed }
+    }
+
+    pub fn text(&self) -> String {
+        self.textarea.lines().join("\n")
+    }
+
+    pub fn input(&mut self, key: KeyEvent) {
+        if self.textarea.input(key) {
+            self.validate();
+        }
+    }
+
+    pub fn insert_newline(&mut self) {
+        self.textarea.insert_newline();
+        self.validate();
+    }
+
+    pub fn paste(&mut self, text: &str) {
+        if self
+            .textarea
+            .insert_str(text.replace("\r\n", "\n").replace('\r', "\n"))
+        {
+            self.validate();
+        }
+    }
+
+    pub fn set_focused(&mut self, focused: bool) {
+        self.textarea.set_cursor_style(match focused {
+            true => Style::default().add_modifier(Modifier::REVERSED),
+            false => Style::default(),
+        });
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.parsed.is_ok()
+    }
+
+    fn validate(&mut self) {
+        self.parsed = serde_json::from_str(&self.text());
+    }
+}
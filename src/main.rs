@@ -1,48 +1,71 @@
 mod app;
+mod backend;
+mod cli;
+mod commands;
 mod config;
+mod editor;
+mod input;
+mod path;
 mod requests;
 mod ui;
+mod worker;
 
 use crate::{
-    app::{App, CurrentScreen, CurrentlyAdding, CurrentlyDeleting, CurrentlyEditing},
+    app::{
+        App, CurrentScreen, CurrentlyAdding, CurrentlyDeleting, CurrentlyEditing, CurrentlyMoving,
+        Field, entries,
+    },
+    backend::{DataBackend, FileBackend, HttpBackend},
+    cli::Cli,
     config::Config,
-    requests::api_get,
+    editor::edit_in_editor,
+    input::JsonInput,
+    path::Segment,
     ui::ui,
 };
+use clap::Parser;
 use color_eyre::eyre::OptionExt;
 use ratatui::{
     Terminal,
     crossterm::{
-        event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind},
+        event::{
+            self, DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste,
+            EnableMouseCapture, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers,
+        },
         execute,
         terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
     },
     prelude::{Backend, CrosstermBackend},
     widgets::ListState,
 };
-use serde_json::{Map, Value};
-use std::io::{self, Error, ErrorKind};
+use serde_json::Value;
+use std::{
+    io,
+    time::{Duration, Instant},
+};
+
+const SPINNER_TICK: Duration = Duration::from_millis(100);
 
 fn main() -> color_eyre::Result<()> {
     color_eyre::install()?;
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    let cli = Cli::parse();
+    let config = Config::init(&cli)?;
+    let backend: Box<dyn DataBackend> = match &cli.file {
+        Some(path) => Box::new(FileBackend::open(path.clone())?),
+        None => Box::new(HttpBackend::new(&config)?),
+    };
+    if let Some(command) = cli.command {
+        return commands::run(command, &config, backend);
+    }
+    let mut app = App::new(config, backend)?;
 
-    let backend = CrosstermBackend::new(stdout);
+    resume_terminal()?;
+    let backend = CrosstermBackend::new(io::stdout());
     let mut terminal = Terminal::new(backend)?;
 
-    let json = api_get("")?;
-    let config = Config::init()?;
-    let mut app = App::new(json, config);
     let result = run_app(&mut terminal, &mut app);
 
-    disable_raw_mode()?;
-    execute!(
-        terminal.backend_mut(),
-        LeaveAlternateScreen,
-        DisableMouseCapture
-    )?;
+    suspend_terminal()?;
     terminal.show_cursor()?;
 
     if let Err(err) = result {
@@ -52,209 +75,415 @@ fn main() -> color_eyre::Result<()> {
     Ok(())
 }
 
+fn resume_terminal() -> io::Result<()> {
+    enable_raw_mode()?;
+    execute!(
+        io::stdout(),
+        EnterAlternateScreen,
+        EnableMouseCapture,
+        EnableBracketedPaste
+    )
+}
+
+fn suspend_terminal() -> io::Result<()> {
+    disable_raw_mode()?;
+    execute!(
+        io::stdout(),
+        LeaveAlternateScreen,
+        DisableMouseCapture,
+        DisableBracketedPaste
+    )
+}
+
 fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> color_eyre::Result<()> {
     let mut list_state = ListState::default().with_selected(Some(0));
+    let mut last_refresh = Instant::now();
     loop {
-        let json = app
-            .json
-            .pointer(
-                &app.key_path
-                    .iter()
-                    .map(|v| format!("/{}", v))
-                    .collect::<String>(),
-            )
-            .ok_or_eyre("Pointer DNE")?;
-        let json_map = match json {
-            Value::Object(j) => Some(j),
-            Value::Array(j) => Some(&Map::from_iter(j.iter().enumerate().map(|(i, v)| {
-                (
-                    format!("{:0fill$}", i, fill = j.len().to_string().len()),
-                    v.clone(),
-                )
-            }))),
-            _ => None,
+        if let Err(err) = app.poll() {
+            app.show_error(err);
+        }
+        let json = app.current().unwrap_or(app.view());
+        let json_entries = entries(json).unwrap_or_default();
+        terminal.draw(|f| ui(f, app, &mut list_state, json, &json_entries))?;
+        let timeout = match app.config.refresh_interval {
+            _ if app.is_busy() => Some(SPINNER_TICK),
+            Some(interval) => Some(interval.saturating_sub(last_refresh.elapsed())),
+            None => None,
         };
-        terminal.try_draw(|f| {
-            ui(
-                f,
-                app,
-                &mut list_state,
-                json,
-                json_map.ok_or(Error::new(ErrorKind::InvalidData, "Not an Array or Object"))?,
-            )
-        })?;
-        if let Event::Key(key) = event::read()? {
-            if key.kind == event::KeyEventKind::Release {
+        if let Some(timeout) = timeout
+            && !event::poll(timeout)?
+        {
+            if let Some(interval) = app.config.refresh_interval
+                && last_refresh.elapsed() >= interval
+            {
+                if app.is_idle() {
+                    app.refresh();
+                }
+                last_refresh = Instant::now();
+            }
+            continue;
+        }
+        let event = event::read()?;
+        if app.request.is_some() {
+            if let Event::Key(key) = event
+                && key.kind == KeyEventKind::Press
+            {
+                match key.code {
+                    KeyCode::Esc => app.cancel(),
+                    KeyCode::Char('q') => return Ok(()),
+                    _ => {}
+                }
+            }
+            continue;
+        }
+        if let Event::Paste(text) = &event
+            && app.error.is_none()
+            && app.conflict.is_none()
+        {
+            app.paste(text);
+        }
+        if let Event::Key(key) = event {
+            if app.error.is_some() {
+                if key.kind == KeyEventKind::Press
+                    && matches!(key.code, KeyCode::Esc | KeyCode::Enter)
+                {
+                    app.error = None;
+                }
                 continue;
             }
-            match app.current_screen {
-                CurrentScreen::Main => match key.code {
-                    KeyCode::Char('j') | KeyCode::Down => list_state.select_next(),
-                    KeyCode::Char('k') | KeyCode::Up => list_state.select_previous(),
-                    KeyCode::Char('[') => list_state.select_first(),
-                    KeyCode::Char(']') => list_state.select_last(),
-                    KeyCode::Char('l') | KeyCode::Right => {
-                        if let Some(json) = json_map {
-                            let selected = json
-                                .keys()
-                                .nth(list_state.selected().ok_or_eyre("No Item Selected")?)
-                                .ok_or_eyre("Out of Range")?
-                                .to_string();
-                            if json
-                                .get(&selected)
-                                .ok_or_eyre("Couldn't Find Value")?
-                                .is_object()
-                                || json
-                                    .get(&selected)
-                                    .ok_or_eyre("Couldn't Find Value")?
-                                    .is_array()
-                            {
-                                let trimmed = match selected.trim_start_matches('0') {
-                                    "" => "0",
-                                    s => s,
-                                };
-                                app.key_path.push(trimmed.to_string());
-                                app.locations
-                                    .push(list_state.selected().ok_or_eyre("No Item Selected")?);
-                                list_state.select(Some(0));
-                            }
-                        }
+            if app.conflict.is_some() {
+                if key.kind == KeyEventKind::Press
+                    && let Err(err) = handle_conflict_key(app, key)
+                {
+                    app.show_error(err);
+                }
+                continue;
+            }
+            if key.kind == KeyEventKind::Press
+                && key.code == KeyCode::Char('E')
+                && matches!(app.current_screen, CurrentScreen::Main)
+                && !app.config.read_only
+            {
+                if let Err(err) = edit_externally(terminal, app, &list_state) {
+                    app.show_error(err);
+                }
+                continue;
+            }
+            match handle_key(app, &mut list_state, key) {
+                Ok(true) => return Ok(()),
+                Ok(false) => {}
+                Err(err) => app.show_error(err),
+            }
+        }
+    }
+}
+
+fn edit_externally<B: Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+    list_state: &ListState,
+) -> color_eyre::Result<()> {
+    let (selected, original) = app.selected(list_state.selected())?;
+    let original = original.clone();
+
+    suspend_terminal()?;
+    let edited = edit_in_editor(&original);
+    resume_terminal()?;
+    terminal.clear()?;
+
+    let value = JsonInput::new(edited?);
+    if matches!(&value.parsed, Ok(parsed) if *parsed == original) {
+        return Ok(());
+    }
+    let valid = value.is_valid();
+    app.currently_editing = Some(CurrentlyEditing::new(selected, value));
+    app.current_screen = CurrentScreen::Editing;
+    if valid {
+        app.save_edited_value()?;
+    }
+    Ok(())
+}
+
+fn handle_conflict_key(app: &mut App, key: KeyEvent) -> color_eyre::Result<()> {
+    match key.code {
+        KeyCode::Char('o') => {
+            app.close_popups();
+            app.overwrite_conflict();
+        }
+        KeyCode::Char('r') => {
+            app.close_popups();
+            app.reload_conflict();
+        }
+        KeyCode::Char('m') => {
+            app.close_popups();
+            app.merge_conflict()?;
+        }
+        KeyCode::Esc => app.conflict = None,
+        _ => {}
+    }
+    Ok(())
+}
+
+fn handle_key(
+    app: &mut App,
+    list_state: &mut ListState,
+    key: KeyEvent,
+) -> color_eyre::Result<bool> {
+    if key.kind == event::KeyEventKind::Release {
+        return Ok(false);
+    }
+    match app.current_screen {
+        CurrentScreen::Main => match key.code {
+            KeyCode::Char('j') | KeyCode::Down => list_state.select_next(),
+            KeyCode::Char('k') | KeyCode::Up => list_state.select_previous(),
+            KeyCode::Char('[') => list_state.select_first(),
+            KeyCode::Char(']') => list_state.select_last(),
+            KeyCode::Char('l') | KeyCode::Right => {
+                let (selected, value) = app.selected(list_state.selected())?;
+                if value.is_object() || value.is_array() {
+                    app.key_path.push(selected);
+                    app.locations
+                        .push(list_state.selected().ok_or_eyre("No Item Selected")?);
+                    list_state.select(Some(0));
+                }
+            }
+            KeyCode::Char('h') | KeyCode::Left if !app.locations.is_empty() => {
+                app.key_path.pop();
+                list_state.select(app.locations.last().copied());
+                app.locations.pop();
+            }
+            KeyCode::Char('e') if !app.config.read_only => {
+                let (selected, value) = app.selected(list_state.selected())?;
+                app.currently_editing = Some(CurrentlyEditing::new(
+                    selected,
+                    JsonInput::new(serde_json::to_string_pretty(value)?),
+                ));
+                app.current_screen = CurrentScreen::Editing;
+            }
+            KeyCode::Char('R') if !app.config.read_only => {
+                let (selected, value) = app.selected(list_state.selected())?;
+                if let Segment::Key(_) = selected {
+                    let mut editing = CurrentlyEditing::new(
+                        selected,
+                        JsonInput::new(serde_json::to_string_pretty(value)?),
+                    );
+                    editing.focus(Field::Key);
+                    app.currently_editing = Some(editing);
+                    app.current_screen = CurrentScreen::Editing;
+                }
+            }
+            KeyCode::Char('a') if !app.config.read_only => {
+                let key = match app.current()? {
+                    Value::Array(array) => Segment::Index(array.len()),
+                    Value::Object(_) => Segment::Key(String::new()),
+                    _ => return Ok(false),
+                };
+                app.current_screen = CurrentScreen::Adding;
+                app.currently_adding =
+                    Some(CurrentlyAdding::new(key, JsonInput::new(String::from(""))));
+            }
+            KeyCode::Char('i') | KeyCode::Char('o') if !app.config.read_only => {
+                let Value::Array(array) = app.current()? else {
+                    return Ok(false);
+                };
+                let len = array.len();
+                let selected = list_state.selected().unwrap_or(0);
+                let index = match key.code {
+                    KeyCode::Char('i') => selected,
+                    _ => selected + 1,
+                };
+                app.current_screen = CurrentScreen::Adding;
+                app.currently_adding = Some(CurrentlyAdding::new(
+                    Segment::Index(index.min(len)),
+                    JsonInput::new(String::from("")),
+                ));
+            }
+            KeyCode::Char('y') => app.yank(list_state.selected())?,
+            KeyCode::Char('x') if !app.config.read_only => app.cut(list_state.selected())?,
+            KeyCode::Char('p') | KeyCode::Char('P')
+                if !app.config.read_only && app.clipboard.is_some() =>
+            {
+                let after = key.code == KeyCode::Char('p');
+                if let Some(index) = app.paste_clipboard(list_state.selected(), after)? {
+                    list_state.select(Some(index));
+                }
+            }
+            KeyCode::Char('d') if !app.config.read_only => {
+                let (selected, _) = app.selected(list_state.selected())?;
+                app.currently_deleting = Some(CurrentlyDeleting {
+                    key: selected,
+                    are_you_sure: false,
+                });
+                app.current_screen = CurrentScreen::Deleting;
+            }
+            KeyCode::Char('J') | KeyCode::Char('K') if !app.config.read_only => {
+                let len = app.current()?.as_array().map_or(0, Vec::len);
+                let from = list_state.selected().ok_or_eyre("No Item Selected")?;
+                let to = match key.code {
+                    KeyCode::Char('J') => Some(from + 1).filter(|to| *to < len),
+                    _ => from.checked_sub(1).filter(|_| from < len),
+                };
+                if let Some(to) = to {
+                    app.move_element(from, to)?;
+                    list_state.select(Some(to));
+                }
+            }
+            KeyCode::Char('m') if !app.config.read_only && app.current()?.is_array() => {
+                app.currently_moving = Some(CurrentlyMoving {
+                    from: list_state.selected().ok_or_eyre("No Item Selected")?,
+                    to: String::new(),
+                });
+                app.current_screen = CurrentScreen::Moving;
+            }
+            KeyCode::Char('u') if !app.config.read_only => {
+                app.undo()?;
+            }
+            KeyCode::Char('r')
+                if key.modifiers.contains(KeyModifiers::CONTROL) && !app.config.read_only =>
+            {
+                app.redo()?;
+            }
+            KeyCode::Char('r') => app.refresh(),
+            KeyCode::Char('s') if app.staging.is_some() => {
+                app.current_screen = CurrentScreen::Reviewing;
+            }
+            KeyCode::Char('q') => return Ok(true),
+            _ => {}
+        },
+        CurrentScreen::Editing if key.kind == KeyEventKind::Press => {
+            let key_error = match app
+                .currently_editing
+                .as_ref()
+                .and_then(|editing| editing.renamed())
+            {
+                Some(name) => app.key_error(name),
+                None => None,
+            };
+            if let Some(editing) = &mut app.currently_editing {
+                match (key.code, &editing.focus, &mut editing.name) {
+                    (KeyCode::Esc, _, _) => {
+                        app.current_screen = CurrentScreen::Main;
+                        app.currently_editing = None;
                     }
-                    KeyCode::Char('h') | KeyCode::Left => {
-                        if app.locations.len() > 0 {
-                            app.key_path.pop();
-                            list_state.select(app.locations.last().copied());
-                            app.locations.pop();
-                        }
+                    (KeyCode::Tab, Field::Key, Some(_)) => editing.focus(Field::Value),
+                    (KeyCode::Tab, Field::Value, Some(_)) => editing.focus(Field::Key),
+                    (KeyCode::Enter, Field::Key, _) if key_error.is_some() => {}
+                    (KeyCode::Enter, Field::Key, _) if editing.value.is_valid() => {
+                        app.save_edited_value()?;
                     }
-                    KeyCode::Char('e') => {
-                        app.current_screen = CurrentScreen::Editing;
-                        if let Some(json) = json_map {
-                            let selected = json
-                                .keys()
-                                .nth(list_state.selected().ok_or_eyre("No Item Selected")?)
-                                .ok_or_eyre("Out of Range")?
-                                .to_string();
-                            app.currently_editing = Some(CurrentlyEditing {
-                                key: selected.clone(),
-                                value: json
-                                    .get(&selected)
-                                    .ok_or_eyre("Couldn't Find Value")?
-                                    .to_string(),
-                                changed: false,
-                            })
-                        }
+                    (KeyCode::Enter, Field::Key, _) => editing.focus(Field::Value),
+                    (_, Field::Key, Some(name)) => input_key(name, key),
+                    (_, Field::Key, None) => {}
+                    (KeyCode::Enter, _, _) if key.modifiers.contains(KeyModifiers::ALT) => {
+                        editing.value.insert_newline();
                     }
-                    KeyCode::Char('a') => {
-                        if let Value::Array(_) = json {
-                            app.current_screen = CurrentScreen::Adding;
-                            app.currently_adding = Some(CurrentlyAdding {
-                                value: String::from(""),
-                            })
-                        }
+                    (KeyCode::Enter, _, _) if key_error.is_some() => editing.focus(Field::Key),
+                    (KeyCode::Enter, _, _) if editing.value.is_valid() => {
+                        app.save_edited_value()?;
                     }
-                    KeyCode::Char('d') => {
-                        app.current_screen = CurrentScreen::Deleting;
-                        if let Some(json) = json_map {
-                            let selected = json
-                                .keys()
-                                .nth(list_state.selected().ok_or_eyre("No Item Selected")?)
-                                .ok_or_eyre("Out of Range")?
-                                .to_string();
-                            app.currently_deleting = Some(CurrentlyDeleting {
-                                key: selected.clone(),
-                                are_you_sure: false,
-                            })
-                        }
+                    (KeyCode::Enter, _, _) => {}
+                    _ => editing.value.input(key),
+                }
+            }
+        }
+        CurrentScreen::Adding if key.kind == KeyEventKind::Press => {
+            let key_error = match app.currently_adding.as_ref().map(|adding| &adding.key) {
+                Some(Segment::Key(name)) => app.key_error(name),
+                _ => None,
+            };
+            if let Some(adding) = &mut app.currently_adding {
+                match (key.code, &adding.focus, &mut adding.key) {
+                    (KeyCode::Esc, _, _) => {
+                        app.current_screen = CurrentScreen::Main;
+                        app.currently_adding = None;
                     }
-                    KeyCode::Char('q') => return Ok(()),
-                    _ => {}
-                },
-                CurrentScreen::Editing if key.kind == KeyEventKind::Press => {
-                    if let Some(editing) = &mut app.currently_editing {
-                        match key.code {
-                            KeyCode::Enter => {
-                                app.save_edited_value()?;
-                                let json = api_get("")?;
-                                app.json = json;
-                                app.currently_editing = None;
-                                app.current_screen = CurrentScreen::Main;
-                            }
-                            KeyCode::Backspace => {
-                                if !editing.changed {
-                                    editing.value = String::from("");
-                                    editing.changed = true;
-                                } else {
-                                    editing.value.pop();
-                                }
-                            }
-                            KeyCode::Esc => {
-                                app.current_screen = CurrentScreen::Main;
-                                app.currently_editing = None;
-                            }
-                            KeyCode::Char(value) => {
-                                if !editing.changed {
-                                    editing.value = String::from("");
-                                    editing.changed = true;
-                                    editing.value.push(value);
-                                } else {
-                                    editing.value.push(value);
-                                }
-                            }
-                            _ => {}
-                        }
+                    (KeyCode::Tab, Field::Key, Segment::Key(_)) => adding.focus(Field::Value),
+                    (KeyCode::Tab, Field::Value, Segment::Key(_)) => adding.focus(Field::Key),
+                    (KeyCode::Enter, Field::Key, _) if key_error.is_some() => {}
+                    (KeyCode::Enter, Field::Key, _) if adding.value.is_valid() => {
+                        app.add_value()?;
+                    }
+                    (KeyCode::Enter, Field::Key, _) => adding.focus(Field::Value),
+                    (_, Field::Key, Segment::Key(name)) => input_key(name, key),
+                    (_, Field::Key, Segment::Index(_)) => {}
+                    (KeyCode::Enter, _, _) if key.modifiers.contains(KeyModifiers::ALT) => {
+                        adding.value.insert_newline();
                     }
+                    (KeyCode::Enter, _, Segment::Key(_)) if key_error.is_some() => {
+                        adding.focus(Field::Key);
+                    }
+                    (KeyCode::Enter, _, _) if adding.value.is_valid() => {
+                        app.add_value()?;
+                    }
+                    (KeyCode::Enter, _, _) => {}
+                    _ => adding.value.input(key),
                 }
-                CurrentScreen::Adding if key.kind == KeyEventKind::Press => {
-                    if let Some(adding) = &mut app.currently_adding {
-                        match key.code {
-                            KeyCode::Enter => {
-                                app.push_object_to_array()?;
-                                let json = api_get("")?;
-                                app.json = json;
-                                app.currently_adding = None;
-                                app.current_screen = CurrentScreen::Main;
-                            }
-                            KeyCode::Backspace => {
-                                adding.value.pop();
-                            }
-                            KeyCode::Esc => {
-                                app.current_screen = CurrentScreen::Main;
-                                app.currently_adding = None;
-                            }
-                            KeyCode::Char(value) => {
-                                adding.value.push(value);
-                            }
-                            _ => {}
-                        }
+            }
+        }
+        CurrentScreen::Deleting if key.kind == KeyEventKind::Press => {
+            if let Some(deleting) = &mut app.currently_deleting {
+                match key.code {
+                    KeyCode::Enter if deleting.are_you_sure => {
+                        app.delete_value()?;
+                    }
+                    KeyCode::Enter => {
+                        app.currently_deleting = None;
+                        app.current_screen = CurrentScreen::Main;
                     }
+                    KeyCode::Esc => {
+                        app.current_screen = CurrentScreen::Main;
+                        app.currently_deleting = None;
+                    }
+                    KeyCode::Tab => match deleting.are_you_sure {
+                        true => deleting.are_you_sure = false,
+                        false => deleting.are_you_sure = true,
+                    },
+                    _ => {}
                 }
-                CurrentScreen::Deleting if key.kind == KeyEventKind::Press => {
-                    if let Some(deleting) = &mut app.currently_deleting {
-                        match key.code {
-                            KeyCode::Enter => {
-                                if deleting.are_you_sure {
-                                    app.delete_value()?;
-                                }
-                                let json = api_get("")?;
-                                app.json = json;
-                                app.currently_deleting = None;
-                                app.current_screen = CurrentScreen::Main;
-                            }
-                            KeyCode::Esc => {
-                                app.current_screen = CurrentScreen::Main;
-                                app.currently_deleting = None;
-                            }
-                            KeyCode::Tab => match deleting.are_you_sure {
-                                true => deleting.are_you_sure = false,
-                                false => deleting.are_you_sure = true,
-                            },
-                            _ => {}
+            }
+        }
+        CurrentScreen::Moving if key.kind == KeyEventKind::Press => {
+            if let Some(moving) = &mut app.currently_moving {
+                match key.code {
+                    KeyCode::Enter => {
+                        let from = moving.from;
+                        if let Ok(to) = app.move_target() {
+                            app.move_element(from, to)?;
+                            list_state.select(Some(to));
                         }
                     }
+                    KeyCode::Esc => {
+                        app.current_screen = CurrentScreen::Main;
+                        app.currently_moving = None;
+                    }
+                    KeyCode::Char(c) if c.is_ascii_digit() => moving.to.push(c),
+                    KeyCode::Backspace => {
+                        moving.to.pop();
+                    }
+                    _ => {}
                 }
-                _ => {}
             }
         }
+        CurrentScreen::Reviewing if key.kind == KeyEventKind::Press => match key.code {
+            KeyCode::Char('c') => app.commit(),
+            KeyCode::Char('D') => {
+                app.discard()?;
+                app.current_screen = CurrentScreen::Main;
+            }
+            KeyCode::Esc => app.current_screen = CurrentScreen::Main,
+            _ => {}
+        },
+        _ => {}
+    }
+    Ok(false)
+}
+
+fn input_key(name: &mut String, key: KeyEvent) {
+    match key.code {
+        KeyCode::Char(c) => name.push(c),
+        KeyCode::Backspace => {
+            name.pop();
+        }
+        _ => {}
     }
 }
@@ -0,0 +1,207 @@
+use color_eyre::eyre::{OptionExt, eyre};
+use reqwest::Url;
+use std::fmt;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+impl Segment {
+    fn parse(token: &str) -> Segment {
+        let is_index = token == "0"
+            || (!token.starts_with('0')
+                && !token.is_empty()
+                && token.bytes().all(|b| b.is_ascii_digit()));
+        match token.parse() {
+            Ok(index) if is_index => Segment::Index(index),
+            _ => Segment::Key(token.to_string()),
+        }
+    }
+
+    // URL parsers drop or collapse these segments, so they would address another node.
+    fn is_addressable(&self) -> bool {
+        !matches!(self, Segment::Key(key) if matches!(key.as_str(), "" | "." | ".."))
+    }
+}
+
+impl fmt::Display for Segment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Segment::Key(key) => write!(f, "{}", key),
+            Segment::Index(index) => write!(f, "{}", index),
+        }
+    }
+}
+
+#[derive(Clone, Default, PartialEq, Eq, Debug)]
+pub struct JsonPath(Vec<Segment>);
+
+impl JsonPath {
+    pub fn root() -> JsonPath {
+        JsonPath(vec![])
+    }
+
+    pub fn parse(pointer: &str) -> color_eyre::Result<JsonPath> {
+        if pointer.is_empty() {
+            return Ok(JsonPath::root());
+        }
+        let tokens = pointer
+            .strip_prefix('/')
+            .ok_or_else(|| eyre!("JSON pointer \"{}\" must start with '/'", pointer))?;
+        Ok(JsonPath(
+            tokens
+                .split('/')
+                .map(|token| Segment::parse(&token.replace("~1", "/").replace("~0", "~")))
+                .collect(),
+        ))
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn push(&mut self, segment: Segment) {
+        self.0.push(segment);
+    }
+
+    pub fn pop(&mut self) -> Option<Segment> {
+        self.0.pop()
+    }
+
+    pub fn join(&self, segment: Segment) -> JsonPath {
+        let mut path = self.clone();
+        path.push(segment);
+        path
+    }
+
+    pub fn starts_with(&self, prefix: &JsonPath) -> bool {
+        self.0.starts_with(&prefix.0)
+    }
+
+    pub fn split_last(&self) -> Option<(JsonPath, &Segment)> {
+        let (last, parent) = self.0.split_last()?;
+        Some((JsonPath(parent.to_vec()), last))
+    }
+
+    pub fn to_pointer(&self) -> String {
+        self.0
+            .iter()
+            .map(|segment| {
+                format!(
+                    "/{}",
+                    segment.to_string().replace('~', "~0").replace('/', "~1")
+                )
+            })
+            .collect()
+    }
+
+    pub fn to_url(&self, base_url: &str) -> color_eyre::Result<Url> {
+        if let Some(segment) = self.0.iter().find(|segment| !segment.is_addressable()) {
+            return Err(eyre!(
+                "key \"{}\" in {} cannot be addressed through the API",
+                segment,
+                self
+            ));
+        }
+        let mut url = Url::parse(base_url)?;
+        url.path_segments_mut()
+            .ok()
+            .ok_or_eyre("Base URL cannot have a path")?
+            .pop_if_empty()
+            .extend(self.0.iter().map(Segment::to_string));
+        Ok(url)
+    }
+}
+
+impl fmt::Display for JsonPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_pointer())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE_URL: &str = "https://example.com/api/data";
+
+    fn key(key: &str) -> Segment {
+        Segment::Key(key.to_string())
+    }
+
+    #[test]
+    fn parses_array_indices() {
+        assert_eq!(Segment::parse("0"), Segment::Index(0));
+        assert_eq!(Segment::parse("12"), Segment::Index(12));
+        assert_eq!(Segment::parse("007"), key("007"));
+        assert_eq!(Segment::parse("-1"), key("-1"));
+        assert_eq!(Segment::parse(""), key(""));
+    }
+
+    #[test]
+    fn parses_pointers() {
+        assert_eq!(JsonPath::parse("").unwrap(), JsonPath::root());
+        assert_eq!(
+            JsonPath::parse("/a/0/007").unwrap(),
+            JsonPath(vec![key("a"), Segment::Index(0), key("007")])
+        );
+        assert_eq!(JsonPath::parse("/").unwrap(), JsonPath(vec![key("")]));
+        assert!(JsonPath::parse("a").is_err());
+    }
+
+    #[test]
+    fn unescapes_tilde_after_slash() {
+        assert_eq!(
+            JsonPath::parse("/a~1b/m~0n/~01").unwrap(),
+            JsonPath(vec![key("a/b"), key("m~n"), key("~1")])
+        );
+    }
+
+    #[test]
+    fn pointer_round_trips() {
+        for pointer in ["/a~1b/m~0n", "/~01", "/sp ace/ünïcødé/0", "/a/"] {
+            assert_eq!(JsonPath::parse(pointer).unwrap().to_pointer(), pointer);
+        }
+    }
+
+    #[test]
+    fn encodes_url_segments() {
+        let path = JsonPath(vec![
+            key("a/b"),
+            key("sp ace"),
+            key("~"),
+            key("ü"),
+            Segment::Index(3),
+        ]);
+        assert_eq!(
+            path.to_url(BASE_URL).unwrap().as_str(),
+            "https://example.com/api/data/a%2Fb/sp%20ace/~/%C3%BC/3"
+        );
+    }
+
+    #[test]
+    fn ignores_trailing_slash_in_base_url() {
+        let path = JsonPath(vec![key("a")]);
+        assert_eq!(
+            path.to_url("https://example.com/api/data/")
+                .unwrap()
+                .as_str(),
+            "https://example.com/api/data/a"
+        );
+        assert_eq!(
+            JsonPath::root().to_url(BASE_URL).unwrap().as_str(),
+            BASE_URL
+        );
+    }
+
+    #[test]
+    fn rejects_unaddressable_keys() {
+        for name in ["", ".", ".."] {
+            let path = JsonPath(vec![key("a"), key(name)]);
+            assert!(path.to_url(BASE_URL).is_err(), "{:?}", name);
+        }
+        assert!(JsonPath(vec![key("...")]).to_url(BASE_URL).is_ok());
+    }
+}
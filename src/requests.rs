@@ -1,42 +1,187 @@
-use reqwest::{self, blocking::Response, header::AUTHORIZATION};
+use crate::{
+    config::Config,
+    path::{JsonPath, Segment},
+};
+use reqwest::{
+    self, Method, StatusCode,
+    blocking::{Client, Response},
+    header::{AUTHORIZATION, RETRY_AFTER},
+};
 use serde_json::Value;
+use std::{error::Error, fmt, thread, time::Duration};
 
-pub fn api_get(path: &str) -> color_eyre::Result<Value> {
-    let url = "https://newnameful.com/api/data";
-    let body = reqwest::blocking::get(format!("{}{}", url, path))?.text()?;
-    Ok(serde_json::from_str(&body)?)
-}
-
-pub fn api_put(path: &str, data: &str, key: &str) -> color_eyre::Result<Response> {
-    let url = format!("https://newnameful.com/api/data{}", path);
-    let client = reqwest::blocking::Client::new();
-    let json: Value = serde_json::from_str(data)?;
-    let request = client
-        .put(url)
-        .header(AUTHORIZATION, format!("Bearer {}", key))
-        .json(&json)
-        .send()?;
-    Ok(request.error_for_status()?)
-}
-
-pub fn api_post(path: &str, data: &str, key: &str) -> color_eyre::Result<Response> {
-    let url = format!("https://newnameful.com/api/data{}", path);
-    let client = reqwest::blocking::Client::new();
-    let json: Value = serde_json::from_str(data)?;
-    let request = client
-        .post(url)
-        .header(AUTHORIZATION, format!("Bearer {}", key))
-        .json(&json)
-        .send()?;
-    Ok(request.error_for_status()?)
-}
-
-pub fn api_delete(path: &str, key: &str) -> color_eyre::Result<Response> {
-    let url = format!("https://newnameful.com/api/data{}", path);
-    let client = reqwest::blocking::Client::new();
-    let request = client
-        .delete(url)
-        .header(AUTHORIZATION, format!("Bearer {}", key))
-        .send()?;
-    Ok(request.error_for_status()?)
+const USER_AGENT: &str = concat!(
+    env!("CARGO_PKG_NAME"),
+    "/",
+    env!("CARGO_PKG_VERSION"),
+    " (+https://github.com/edwark43/nameful-cli)"
+);
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const BACKOFF: Duration = Duration::from_millis(250);
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(30);
+
+#[derive(Debug)]
+pub struct ApiError {
+    pub status: StatusCode,
+    pub body: String,
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "HTTP {}", self.status)?;
+        if !self.body.is_empty() {
+            write!(f, ": {}", self.body)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for ApiError {}
+
+fn check_status(response: Response) -> color_eyre::Result<Response> {
+    let status = response.status();
+    if status.is_client_error() || status.is_server_error() {
+        let body = response.text().unwrap_or_default();
+        return Err(ApiError { status, body }.into());
+    }
+    Ok(response)
+}
+
+fn retry_after(response: &Response) -> Option<Duration> {
+    let seconds = response
+        .headers()
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()?;
+    Some(Duration::from_secs(seconds).min(MAX_RETRY_AFTER))
+}
+
+fn backoff(attempt: u32) -> Duration {
+    BACKOFF
+        .checked_mul(2u32.saturating_pow(attempt))
+        .map_or(MAX_RETRY_AFTER, |backoff| backoff.min(MAX_RETRY_AFTER))
+}
+
+// Deleting or replacing an array index twice hits whichever element shifted into it.
+fn is_repeatable(method: &Method, path: &JsonPath) -> bool {
+    let is_index = matches!(path.split_last(), Some((_, Segment::Index(_))));
+    *method == Method::GET || (*method == Method::PUT && !is_index)
+}
+
+pub struct ApiClient {
+    client: Client,
+    base_url: String,
+    api_key: String,
+    retries: u32,
+}
+
+impl ApiClient {
+    pub fn new(config: &Config) -> color_eyre::Result<ApiClient> {
+        let client = Client::builder()
+            .user_agent(USER_AGENT)
+            .timeout(config.timeout.unwrap_or(DEFAULT_TIMEOUT))
+            .connect_timeout(config.connect_timeout.unwrap_or(DEFAULT_CONNECT_TIMEOUT))
+            .build()?;
+        Ok(ApiClient {
+            client,
+            base_url: config.base_url.clone(),
+            api_key: config.api_key.resolve()?,
+            retries: config.retries,
+        })
+    }
+
+    pub fn get(&self, path: &JsonPath) -> color_eyre::Result<Value> {
+        let body = self.send(Method::GET, path, None)?.text()?;
+        Ok(serde_json::from_str(&body)?)
+    }
+
+    pub fn put(&self, path: &JsonPath, json: &Value) -> color_eyre::Result<Response> {
+        self.send(Method::PUT, path, Some(json))
+    }
+
+    pub fn post(&self, path: &JsonPath, json: &Value) -> color_eyre::Result<Response> {
+        self.send(Method::POST, path, Some(json))
+    }
+
+    pub fn delete(&self, path: &JsonPath) -> color_eyre::Result<Response> {
+        self.send(Method::DELETE, path, None)
+    }
+
+    pub fn patch(&self, path: &JsonPath, json: &Value) -> color_eyre::Result<Response> {
+        self.send(Method::PATCH, path, Some(json))
+    }
+
+    fn send(
+        &self,
+        method: Method,
+        path: &JsonPath,
+        json: Option<&Value>,
+    ) -> color_eyre::Result<Response> {
+        let url = path.to_url(&self.base_url)?;
+        let repeatable = is_repeatable(&method, path);
+        let mut attempt = 0;
+        loop {
+            let mut request = self.client.request(method.clone(), url.clone());
+            if !self.api_key.is_empty() {
+                request = request.header(AUTHORIZATION, format!("Bearer {}", self.api_key));
+            }
+            if let Some(json) = json {
+                request = request.json(json);
+            }
+            let result = request.send();
+            // 429, 503 and connect errors mean the request was not processed, so any method may
+            // be retried. Other failures may have been applied, so only repeatable requests are.
+            let wait = match &result {
+                _ if attempt >= self.retries => None,
+                Ok(response) => match response.status() {
+                    StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE => {
+                        Some(retry_after(response).unwrap_or(backoff(attempt)))
+                    }
+                    status if status.is_server_error() && repeatable => Some(backoff(attempt)),
+                    _ => None,
+                },
+                Err(err) if err.is_connect() || (err.is_timeout() && repeatable) => {
+                    Some(backoff(attempt))
+                }
+                Err(_) => None,
+            };
+            match wait {
+                Some(wait) => {
+                    thread::sleep(wait);
+                    attempt += 1;
+                }
+                None => return check_status(result?),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_cap() {
+        assert_eq!(backoff(0), BACKOFF);
+        assert_eq!(backoff(3), BACKOFF * 8);
+        for attempt in [7, 31, 32, 64, u32::MAX] {
+            assert_eq!(backoff(attempt), MAX_RETRY_AFTER);
+        }
+    }
+
+    #[test]
+    fn repeats_only_requests_that_cannot_apply_twice() {
+        let key = JsonPath::parse("/a/b").unwrap();
+        let index = JsonPath::parse("/a/3").unwrap();
+        assert!(is_repeatable(&Method::GET, &index));
+        assert!(is_repeatable(&Method::PUT, &key));
+        assert!(!is_repeatable(&Method::PUT, &index));
+        assert!(!is_repeatable(&Method::DELETE, &key));
+        assert!(!is_repeatable(&Method::POST, &key));
+        assert!(!is_repeatable(&Method::PATCH, &key));
+    }
 }
@@ -1,22 +1,27 @@
-use crate::app::{App, CurrentScreen};
+use crate::{
+    app::{App, CurrentScreen, Field, Request},
+    input::JsonInput,
+    path::Segment,
+};
 use ratatui::{
     Frame,
     layout::{Alignment, Constraint},
     prelude::{Direction, Layout, Rect},
     style::{Color, Modifier, Style},
-    text::{Line, Span, Text},
+    text::{Line, Span},
     widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap},
 };
-use serde_json::{Map, Value};
-use std::io::{Error, ErrorKind};
+use serde_json::Value;
+
+const SPINNER: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
 
 pub fn ui(
     frame: &mut Frame,
     app: &App,
     list_state: &mut ListState,
     json: &Value,
-    json_map: &Map<String, Value>,
-) -> Result<(), Error> {
+    json_entries: &[(Segment, &Value)],
+) {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
@@ -28,35 +33,69 @@ pub fn ui(
     let title_block = Block::default()
         .borders(Borders::ALL)
         .style(Style::default());
-    let title = Paragraph::new(Text::styled(
-        "Interact with NN API",
-        Style::default().fg(Color::Green),
-    ))
-    .block(title_block);
+    let mut title_spans = vec![
+        Span::styled("Interact with NN API", Style::default().fg(Color::Green)),
+        Span::styled(" | ", Style::default().fg(Color::White)),
+        Span::styled(app.source.clone(), Style::default().fg(Color::LightBlue)),
+    ];
+    if app.config.read_only {
+        title_spans.push(Span::styled(
+            " (read-only)",
+            Style::default().fg(Color::Yellow),
+        ));
+    }
+    let title = Paragraph::new(Line::from(title_spans)).block(title_block);
 
     frame.render_widget(title, chunks[0]);
 
     let mut list_items = Vec::<ListItem>::new();
 
-    for key in json_map.keys() {
-        list_items.push(ListItem::new(Line::from(Span::styled(
-            format!(
-                "{: <25} : {}",
-                key,
-                json_map
-                    .get(key)
-                    .ok_or(Error::new(ErrorKind::NotFound, "Couldn't Find Value"))?
-                    .to_string()
-            ),
-            Style::default(),
-        ))))
+    for (key, value) in json_entries {
+        let style = match key {
+            Segment::Key(_) => Style::default(),
+            Segment::Index(_) => Style::default().fg(Color::Cyan),
+        };
+        let item = ListItem::new(Line::from(vec![
+            Span::styled(format!("{: <25}", key.to_string()), style),
+            Span::raw(format!(" : {}", value)),
+        ]));
+        match app.is_pending(&app.key_path.join(key.clone())) {
+            true => {
+                list_items.push(item.style(Style::default().bg(Color::Yellow).fg(Color::Black)))
+            }
+            false => list_items.push(item),
+        }
     }
 
     let list = List::new(list_items).highlight_style(Modifier::REVERSED);
 
-    frame.render_stateful_widget(list, chunks[1], list_state);
+    let body_chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(70), Constraint::Percentage(30)])
+        .split(chunks[1]);
+
+    frame.render_stateful_widget(list, body_chunks[0], list_state);
 
-    let current_navigation_text = vec![
+    let mut history_items = Vec::<ListItem>::new();
+    for operation in &app.undone {
+        history_items.push(ListItem::new(Span::styled(
+            operation.describe(),
+            Style::default().fg(Color::DarkGray),
+        )));
+    }
+    for operation in app.history.iter().rev() {
+        history_items.push(ListItem::new(Span::raw(operation.describe())));
+    }
+    let history = List::new(history_items).block(
+        Block::default()
+            .title("History")
+            .title_bottom(Line::from("(u)ndo / (Ctrl-r)edo").right_aligned())
+            .borders(Borders::LEFT),
+    );
+
+    frame.render_widget(history, body_chunks[1]);
+
+    let mut current_navigation_text = vec![
         match app.current_screen {
             CurrentScreen::Main => {
                 Span::styled("Normal Mode", Style::default().fg(Color::DarkGray))
@@ -68,6 +107,12 @@ pub fn ui(
             CurrentScreen::Deleting => {
                 Span::styled("Deleting Mode", Style::default().fg(Color::Red))
             }
+            CurrentScreen::Moving => {
+                Span::styled("Moving Mode", Style::default().fg(Color::Magenta))
+            }
+            CurrentScreen::Reviewing => {
+                Span::styled("Reviewing Mode", Style::default().fg(Color::Yellow))
+            }
         }
         .to_owned(),
         Span::styled(" | ", Style::default().fg(Color::White)),
@@ -84,36 +129,116 @@ pub fn ui(
             CurrentScreen::Deleting => {
                 Span::styled("Deleting Json Pair", Style::default().fg(Color::LightRed))
             }
+            CurrentScreen::Moving => Span::styled(
+                "Moving Array Element",
+                Style::default().fg(Color::LightMagenta),
+            ),
+            CurrentScreen::Reviewing => {
+                Span::styled("Reviewing Changes", Style::default().fg(Color::LightYellow))
+            }
         },
     ];
+    if let Some(staging) = &app.staging {
+        current_navigation_text.push(Span::styled(" | ", Style::default().fg(Color::White)));
+        current_navigation_text.push(Span::styled(
+            format!("{} pending", staging.pending.len()),
+            Style::default().fg(Color::Yellow),
+        ));
+    }
+    if let Some((key, _)) = &app.clipboard {
+        current_navigation_text.push(Span::styled(" | ", Style::default().fg(Color::White)));
+        current_navigation_text.push(Span::styled(
+            format!("clipboard: {}", key),
+            Style::default().fg(Color::Gray),
+        ));
+    }
+    if let Some(request) = &app.request {
+        let frame = request.started.elapsed().as_millis() as usize / 100 % SPINNER.len();
+        current_navigation_text.push(Span::styled(" | ", Style::default().fg(Color::White)));
+        current_navigation_text.push(Span::styled(
+            format!("{} {}...", SPINNER[frame], request.label),
+            Style::default().fg(Color::LightCyan),
+        ));
+    }
 
     let mode_footer = Paragraph::new(Line::from(current_navigation_text))
         .block(Block::default().borders(Borders::ALL));
 
     let current_keys_hint = {
         match app.current_screen {
-            CurrentScreen::Main => match json {
-                Value::Array(_) => Span::styled(
-                    "(q)uit / (e)dit / (a)dd / (d)elete",
-                    Style::default().fg(Color::Red),
-                ),
-                _ => Span::styled(
-                    "(q)uit / (e)dit / (d)elete",
+            _ if app.request.as_ref().is_some_and(Request::is_cancellable) => Span::styled(
+                "(ESC) to cancel request / (q)uit",
+                Style::default().fg(Color::Red),
+            ),
+            _ if app.request.is_some() => Span::styled(
+                "Waiting for the commit to finish / (q)uit",
+                Style::default().fg(Color::Red),
+            ),
+            CurrentScreen::Main if app.config.read_only => {
+                Span::styled("(q)uit / (y)ank", Style::default().fg(Color::Red))
+            }
+            CurrentScreen::Main => {
+                let mut hint = String::from("(q)uit / (r)efresh / (e)dit / (E)ditor");
+                if json.is_object() {
+                    hint.push_str(" / (R)ename");
+                }
+                if json.is_array() || json.is_object() {
+                    hint.push_str(" / (a)dd");
+                }
+                if json.is_array() {
+                    hint.push_str(" / (i)nsert / (o) after / (J/K) reorder / (m)ove to");
+                }
+                hint.push_str(" / (d)elete / (y)ank / (x) cut");
+                if app.clipboard.is_some() {
+                    hint.push_str(" / (p/P)aste");
+                }
+                if app.staging.is_some() {
+                    hint.push_str(" / (s)taged changes");
+                }
+                Span::styled(hint, Style::default().fg(Color::Red))
+            }
+            CurrentScreen::Editing
+                if app
+                    .currently_editing
+                    .as_ref()
+                    .is_some_and(|editing| editing.name.is_some()) =>
+            {
+                Span::styled(
+                    "(ESC) to cancel / (Tab) to switch / (Alt+Enter) newline / (Enter) to write pair",
                     Style::default().fg(Color::Red),
-                ),
-            },
+                )
+            }
             CurrentScreen::Editing => Span::styled(
-                "(ESC) to cancel / (Enter) to write value",
+                "(ESC) to cancel / (Alt+Enter) newline / (Enter) to write value",
                 Style::default().fg(Color::Red),
             ),
+            CurrentScreen::Adding
+                if app
+                    .currently_adding
+                    .as_ref()
+                    .is_some_and(|adding| matches!(adding.key, Segment::Key(_))) =>
+            {
+                Span::styled(
+                    "(ESC) to cancel / (Tab) to switch / (Alt+Enter) newline / (Enter) to add pair",
+                    Style::default().fg(Color::Red),
+                )
+            }
             CurrentScreen::Adding => Span::styled(
-                "(ESC) to cancel / (Enter) to push value",
+                "(ESC) to cancel / (Alt+Enter) newline / (Enter) to push value",
                 Style::default().fg(Color::Red),
             ),
             CurrentScreen::Deleting => Span::styled(
                 "(ESC) to cancel / (Tab) to switch / (Enter) to confirm",
                 Style::default().fg(Color::Red),
             ),
+            CurrentScreen::Moving => Span::styled(
+                "(ESC) to cancel / (Enter) to move",
+                Style::default().fg(Color::Red),
+            ),
+            CurrentScreen::Reviewing => Span::styled(
+                "(ESC) to go back / (c)ommit / (D)iscard",
+                Style::default().fg(Color::Red),
+            ),
         }
     };
 
@@ -140,56 +265,84 @@ pub fn ui(
         _ => {}
     }
 
-    let area = centered_rect(60, 25, frame.area());
+    let area = centered_rect(70, 40, frame.area());
 
     let popup_chunks = Layout::default()
         .direction(Direction::Horizontal)
         .margin(1)
         .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
         .split(area);
-    let key_block = Block::default().title("Key").borders(Borders::ALL);
-    let value_block = Block::default()
-        .title("Value")
-        .borders(Borders::ALL)
-        .style(Style::default().bg(Color::LightYellow).fg(Color::Black));
+    let focused_style = Style::default().bg(Color::LightYellow).fg(Color::Black);
     if let Some(editing) = &app.currently_editing {
         frame.render_widget(Clear, area);
         frame.render_widget(&popup_block, area);
-        let trimmed = match editing.key.trim_start_matches('0') {
-            "" => "0",
-            s => s,
-        };
-        let key_text = Paragraph::new(trimmed).block(key_block);
-        let value_text = Paragraph::new(editing.value.clone())
-            .block(value_block)
-            .wrap(Wrap { trim: false });
+        let mut key_block = Block::default().title("Key").borders(Borders::ALL);
+        let mut value_block = Block::default()
+            .title("Value")
+            .borders(Borders::ALL)
+            .title_bottom(validation_line(&editing.value));
+        match editing.focus {
+            Field::Key => key_block = key_block.style(focused_style),
+            Field::Value => value_block = value_block.style(focused_style),
+        }
+
+        let key_text = match &editing.name {
+            Some(name) => {
+                key_block = key_block.title_bottom(match editing.renamed() {
+                    Some(name) => match app.key_error(name) {
+                        Some(err) => Line::styled(err, Style::default().fg(Color::Red)),
+                        None => Line::styled("rename", Style::default().fg(Color::Green)),
+                    },
+                    None => Line::default(),
+                });
+                key_input(name, editing.focus == Field::Key)
+            }
+            None => Paragraph::new(editing.key.to_string()),
+        }
+        .block(key_block);
 
         frame.render_widget(key_text, popup_chunks[0]);
-        frame.render_widget(value_text, popup_chunks[1]);
+        let value_area = value_block.inner(popup_chunks[1]);
+        frame.render_widget(value_block, popup_chunks[1]);
+        frame.render_widget(&editing.value.textarea, value_area);
     }
 
     if let Some(adding) = &app.currently_adding {
         frame.render_widget(Clear, area);
         frame.render_widget(&popup_block, area);
-        let key_block = Block::default().title("Key").borders(Borders::ALL);
-        let value_block = Block::default()
+        let mut key_block = Block::default().title("Key").borders(Borders::ALL);
+        let mut value_block = Block::default()
             .title("Value")
             .borders(Borders::ALL)
-            .style(Style::default().bg(Color::LightYellow).fg(Color::Black));
-
-        let key_text = Paragraph::new(format!(
-            "{}",
-            json.as_array()
-                .ok_or(Error::new(ErrorKind::InvalidData, "Not an Array"))?
-                .len()
-        ))
+            .title_bottom(validation_line(&adding.value));
+        match adding.focus {
+            Field::Key => key_block = key_block.style(focused_style),
+            Field::Value => value_block = value_block.style(focused_style),
+        }
+
+        let key_text = match &adding.key {
+            Segment::Key(key) => {
+                key_block = key_block.title_bottom(match app.key_error(key) {
+                    Some(err) => Line::styled(err, Style::default().fg(Color::Red)),
+                    None => Line::styled("new key", Style::default().fg(Color::Green)),
+                });
+                key_input(key, adding.focus == Field::Key)
+            }
+            Segment::Index(index) => {
+                let len = json.as_array().map_or(0, Vec::len);
+                key_block = key_block.title_bottom(match *index == len {
+                    true => "append",
+                    false => "insert",
+                });
+                Paragraph::new(index.to_string())
+            }
+        }
         .block(key_block);
-        let value_text = Paragraph::new(adding.value.clone())
-            .block(value_block)
-            .wrap(Wrap { trim: false });
 
         frame.render_widget(key_text, popup_chunks[0]);
-        frame.render_widget(value_text, popup_chunks[1]);
+        let value_area = value_block.inner(popup_chunks[1]);
+        frame.render_widget(value_block, popup_chunks[1]);
+        frame.render_widget(&adding.value.textarea, value_area);
     }
 
     if let Some(deleting) = &app.currently_deleting {
@@ -210,7 +363,141 @@ pub fn ui(
         frame.render_widget(yes_text, popup_chunks[0]);
         frame.render_widget(no_text, popup_chunks[1]);
     }
-    Ok(())
+
+    if let Some(moving) = &app.currently_moving {
+        let area = centered_rect(40, 20, frame.area());
+        let bottom = match app.move_target() {
+            Ok(to) => Line::styled(
+                format!("move {} to {}", moving.from, to),
+                Style::default().fg(Color::Green),
+            ),
+            Err(err) => Line::styled(err.to_string(), Style::default().fg(Color::Red)),
+        };
+        let input = key_input(&moving.to, true).block(
+            Block::default()
+                .title("Move to index")
+                .title_bottom(bottom)
+                .borders(Borders::ALL)
+                .style(focused_style),
+        );
+        frame.render_widget(Clear, area);
+        frame.render_widget(input, area);
+    }
+
+    if let (CurrentScreen::Reviewing, Some(staging)) = (&app.current_screen, &app.staging) {
+        let area = centered_rect(80, 80, frame.area());
+        let mut lines = Vec::<Line>::new();
+        for operation in &staging.pending {
+            lines.push(Line::styled(
+                operation.describe(),
+                Style::default().add_modifier(Modifier::BOLD),
+            ));
+            if let Some(previous) = &operation.previous {
+                lines.push(Line::styled(
+                    format!("- {}", previous),
+                    Style::default().fg(Color::Red),
+                ));
+            }
+            if let Some(new) = &operation.new {
+                lines.push(Line::styled(
+                    format!("+ {}", new),
+                    Style::default().fg(Color::Green),
+                ));
+            }
+        }
+        if lines.is_empty() {
+            lines.push(Line::from("No pending changes"));
+        }
+        let review_text = Paragraph::new(lines)
+            .block(
+                Block::default()
+                    .title("Pending changes")
+                    .title_alignment(Alignment::Center)
+                    .borders(Borders::ALL),
+            )
+            .wrap(Wrap { trim: false });
+
+        frame.render_widget(Clear, area);
+        frame.render_widget(review_text, area);
+    }
+
+    if let Some(conflict) = &app.conflict {
+        let area = centered_rect(90, 70, frame.area());
+        let conflict_block = Block::default()
+            .title(format!("Conflict at {}", conflict.operation.path))
+            .title_bottom(
+                Line::from("(o)verwrite / (r)eload / (m)erge / (ESC) to cancel").centered(),
+            )
+            .title_alignment(Alignment::Center)
+            .borders(Borders::ALL)
+            .style(Style::default().bg(Color::DarkGray));
+        let conflict_chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .margin(1)
+            .constraints([
+                Constraint::Ratio(1, 3),
+                Constraint::Ratio(1, 3),
+                Constraint::Ratio(1, 3),
+            ])
+            .split(area);
+
+        frame.render_widget(Clear, area);
+        frame.render_widget(conflict_block, area);
+        for (index, (title, value)) in [
+            ("Loaded", &conflict.operation.previous),
+            ("Remote", &conflict.remote),
+            ("Mine", &conflict.operation.new),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let text = match value {
+                Some(value) => serde_json::to_string_pretty(value).unwrap_or_default(),
+                None => String::from("(deleted)"),
+            };
+            let pane = Paragraph::new(text)
+                .block(Block::default().title(title).borders(Borders::ALL))
+                .wrap(Wrap { trim: false });
+            frame.render_widget(pane, conflict_chunks[index]);
+        }
+    }
+
+    if let Some(error) = &app.error {
+        let area = centered_rect(60, 40, frame.area());
+        let error_block = Block::default()
+            .title(error.title.as_str())
+            .title_bottom(Line::from("(ESC/Enter) to dismiss").centered())
+            .title_alignment(Alignment::Center)
+            .borders(Borders::ALL)
+            .style(Style::default().bg(Color::Red).fg(Color::White));
+        let error_text = Paragraph::new(error.message.as_str())
+            .block(error_block)
+            .wrap(Wrap { trim: false });
+
+        frame.render_widget(Clear, area);
+        frame.render_widget(error_text, area);
+    }
+}
+
+fn key_input(name: &str, focused: bool) -> Paragraph<'static> {
+    let mut spans = vec![Span::raw(name.to_string())];
+    if focused {
+        spans.push(Span::styled(
+            " ",
+            Style::default().add_modifier(Modifier::REVERSED),
+        ));
+    }
+    Paragraph::new(Line::from(spans))
+}
+
+fn validation_line(input: &JsonInput) -> Line<'static> {
+    match &input.parsed {
+        Ok(_) => Line::styled("valid", Style::default().fg(Color::Green)),
+        Err(err) => Line::styled(
+            format!("invalid at {}:{}", err.line(), err.column()),
+            Style::default().fg(Color::Red),
+        ),
+    }
 }
 
 fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
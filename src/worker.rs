@@ -0,0 +1,52 @@
+use crate::backend::DataBackend;
+use std::{
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+};
+
+pub type Job<T> = Box<dyn FnOnce(&mut dyn DataBackend) -> T + Send>;
+
+pub struct Worker<T> {
+    jobs: Sender<(u64, Job<T>)>,
+    results: Receiver<(u64, T)>,
+    next_id: u64,
+    outstanding: usize,
+}
+
+impl<T: Send + 'static> Worker<T> {
+    pub fn spawn(mut backend: Box<dyn DataBackend>) -> Worker<T> {
+        let (jobs, queue) = mpsc::channel::<(u64, Job<T>)>();
+        let (finished, results) = mpsc::channel();
+        thread::spawn(move || {
+            for (id, job) in queue {
+                if finished.send((id, job(backend.as_mut()))).is_err() {
+                    break;
+                }
+            }
+        });
+        Worker {
+            jobs,
+            results,
+            next_id: 0,
+            outstanding: 0,
+        }
+    }
+
+    pub fn send(&mut self, job: Job<T>) -> u64 {
+        self.next_id += 1;
+        if self.jobs.send((self.next_id, job)).is_ok() {
+            self.outstanding += 1;
+        }
+        self.next_id
+    }
+
+    pub fn try_recv(&mut self) -> Option<(u64, T)> {
+        let result = self.results.try_recv().ok()?;
+        self.outstanding -= 1;
+        Some(result)
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.outstanding > 0
+    }
+}